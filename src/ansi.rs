@@ -0,0 +1,322 @@
+use std::io::{ self, Write };
+use std::mem;
+use std::thread;
+use std::time::{ Duration, Instant };
+
+use libc;
+use tcod::colors::Color;
+use tcod::input::{ Key, KeyCode, Event, Mouse };
+
+use renderer::{ Renderer, Cell, BLANK };
+
+// Alternate screen, hidden cursor and SGR mouse reporting, plus their inverses.
+const ENTER_SCREEN: &str = "\x1b[?1049h\x1b[?25l\x1b[?1003h\x1b[?1006h\x1b[2J";
+const LEAVE_SCREEN: &str = "\x1b[0m\x1b[?1006l\x1b[?1003l\x1b[?25h\x1b[?1049l";
+
+// Draws to a plain terminal with ANSI escape sequences and reads keys from
+// stdin, so the game can be played over SSH. Only the cells that changed since
+// the last flush are written out.
+pub struct AnsiRenderer {
+    width: i32,
+    height: i32,
+    cells: Vec<Cell>,
+    shown: Vec<Option<Cell>>,
+    input: Vec<u8>,
+    mouse: Mouse,
+    closed: bool,
+    frame: Duration,
+    last_flush: Instant,
+    original_termios: libc::termios,
+}
+
+impl AnsiRenderer {
+    pub fn new(width: i32, height: i32, fps: i32) -> AnsiRenderer {
+        let original_termios = unsafe {
+            let mut termios: libc::termios = mem::zeroed();
+            libc::tcgetattr(libc::STDIN_FILENO, &mut termios);
+            let mut raw = termios;
+            raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+            raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+            raw.c_cc[libc::VMIN] = 0;
+            raw.c_cc[libc::VTIME] = 0;
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw);
+            termios
+        };
+
+        let size = (width * height) as usize;
+        let renderer = AnsiRenderer {
+            width,
+            height,
+            cells: vec![BLANK; size],
+            shown: vec![None; size],
+            input: vec![],
+            mouse: Default::default(),
+            closed: false,
+            frame: Duration::from_millis(1000 / fps as u64),
+            last_flush: Instant::now(),
+            original_termios,
+        };
+        renderer.write(ENTER_SCREEN);
+        renderer
+    }
+
+    fn write(&self, text: &str) {
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+        let _ = out.write_all(text.as_bytes());
+        let _ = out.flush();
+    }
+
+    fn cell_mut(&mut self, x: i32, y: i32) -> Option<&mut Cell> {
+        if x >= 0 && y >= 0 && x < self.width && y < self.height {
+            Some(&mut self.cells[(y * self.width + x) as usize])
+        } else {
+            None
+        }
+    }
+
+    fn wait_for_next_frame(&mut self) {
+        let elapsed = self.last_flush.elapsed();
+        if elapsed < self.frame {
+            thread::sleep(self.frame - elapsed);
+        }
+        self.last_flush = Instant::now();
+    }
+
+    fn read_input(&mut self) {
+        let mut buffer = [0u8; 256];
+        loop {
+            let count = unsafe {
+                libc::read(libc::STDIN_FILENO, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len())
+            };
+            if count <= 0 {
+                break;
+            }
+            self.input.extend_from_slice(&buffer[..count as usize]);
+        }
+    }
+
+    // Takes the next complete key or mouse event off the input buffer.
+    fn next_event(&mut self) -> Option<Event> {
+        while !self.input.is_empty() {
+            let (consumed, event) = parse_input(&self.input)?;
+            self.input.drain(..consumed);
+            match event {
+                Input::Key(key) => return Some(Event::Key(key)),
+                Input::Mouse { button, x, y, released } => {
+                    self.mouse.cx = x as isize;
+                    self.mouse.cy = y as isize;
+                    self.mouse.x = x as isize;
+                    self.mouse.y = y as isize;
+                    self.mouse.lbutton_pressed = released && button == 0;
+                    self.mouse.rbutton_pressed = released && button == 2;
+                    return Some(Event::Mouse(self.mouse));
+                },
+                Input::Interrupt => {
+                    self.closed = true;
+                    return None;
+                },
+                Input::Ignored => {},
+            }
+        }
+        None
+    }
+}
+
+impl Drop for AnsiRenderer {
+    fn drop(&mut self) {
+        self.write(LEAVE_SCREEN);
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original_termios);
+        }
+    }
+}
+
+impl Renderer for AnsiRenderer {
+    fn width(&self) -> i32 {
+        self.width
+    }
+
+    fn height(&self) -> i32 {
+        self.height
+    }
+
+    fn clear(&mut self) {
+        for cell in &mut self.cells {
+            *cell = BLANK;
+        }
+    }
+
+    fn put_char(&mut self, x: i32, y: i32, c: char, color: Color) {
+        if let Some(cell) = self.cell_mut(x, y) {
+            cell.c = c;
+            cell.fg = color;
+        }
+    }
+
+    fn set_background(&mut self, x: i32, y: i32, color: Color) {
+        if let Some(cell) = self.cell_mut(x, y) {
+            cell.bg = color;
+        }
+    }
+
+    fn print(&mut self, x: i32, y: i32, text: &str, color: Color) {
+        for (i, c) in text.chars().enumerate() {
+            self.put_char(x + i as i32, y, c, color);
+        }
+    }
+
+    fn flush(&mut self) {
+        let mut out = String::new();
+        let mut cursor = None;
+        let mut colors = None;
+        for (index, &cell) in self.cells.iter().enumerate() {
+            if self.shown[index] == Some(cell) {
+                continue;
+            }
+            if cursor != Some(index) {
+                let (x, y) = (index as i32 % self.width, index as i32 / self.width);
+                out.push_str(&format!("\x1b[{};{}H", y + 1, x + 1));
+            }
+            if colors != Some((cell.fg, cell.bg)) {
+                out.push_str(&format!("\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m",
+                        cell.fg.r, cell.fg.g, cell.fg.b, cell.bg.r, cell.bg.g, cell.bg.b));
+                colors = Some((cell.fg, cell.bg));
+            }
+            out.push(cell.c);
+            cursor = Some(index + 1);
+            self.shown[index] = Some(cell);
+        }
+        if !out.is_empty() {
+            self.write(&out);
+        }
+        self.wait_for_next_frame();
+    }
+
+    fn check_for_event(&mut self) -> Option<Event> {
+        self.mouse.lbutton_pressed = false;
+        self.mouse.rbutton_pressed = false;
+        self.read_input();
+        self.next_event()
+    }
+
+    fn wait_for_keypress(&mut self) -> Key {
+        loop {
+            self.read_input();
+            if let Some(Event::Key(key)) = self.next_event() {
+                return key;
+            }
+            if self.closed {
+                return Key { code: KeyCode::Escape, ..Default::default() };
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn window_closed(&self) -> bool {
+        self.closed
+    }
+
+    fn idle(&mut self) {
+        self.wait_for_next_frame();
+    }
+}
+
+enum Input {
+    Key(Key),
+    Mouse { button: u32, x: i32, y: i32, released: bool },
+    Interrupt,
+    Ignored,
+}
+
+fn key(code: KeyCode) -> Key {
+    Key { code, pressed: true, ..Default::default() }
+}
+
+fn printable_key(c: char) -> Key {
+    let code = match c {
+        ' ' => KeyCode::Spacebar,
+        '0' => KeyCode::Number0,
+        '1' => KeyCode::Number1,
+        '2' => KeyCode::Number2,
+        '3' => KeyCode::Number3,
+        '4' => KeyCode::Number4,
+        '5' => KeyCode::Number5,
+        '6' => KeyCode::Number6,
+        '7' => KeyCode::Number7,
+        '8' => KeyCode::Number8,
+        '9' => KeyCode::Number9,
+        _ => KeyCode::Char,
+    };
+    Key { printable: c, shift: c.is_ascii_uppercase(), ..key(code) }
+}
+
+// Parses one event from the front of `input`, returning how many bytes it
+// used. Returns None if an escape sequence has only partly arrived.
+fn parse_input(input: &[u8]) -> Option<(usize, Input)> {
+    match input[0] {
+        0x1b => parse_escape(input),
+        b'\r' | b'\n' => Some((1, Input::Key(key(KeyCode::Enter)))),
+        b'\t' => Some((1, Input::Key(key(KeyCode::Tab)))),
+        0x7f | 0x08 => Some((1, Input::Key(key(KeyCode::Backspace)))),
+        0x03 => Some((1, Input::Interrupt)),
+        byte @ 0x20..=0x7e => Some((1, Input::Key(printable_key(byte as char)))),
+        _ => Some((1, Input::Ignored)),
+    }
+}
+
+fn parse_escape(input: &[u8]) -> Option<(usize, Input)> {
+    if input.len() == 1 {
+        return Some((1, Input::Key(key(KeyCode::Escape))));
+    }
+    match input[1] {
+        b'[' | b'O' => {
+            let end = input[2..].iter().position(|b| (0x40..=0x7e).contains(b))? + 2;
+            let params = String::from_utf8_lossy(&input[2..end]).into_owned();
+            let event = match params.strip_prefix('<') {
+                Some(mouse) => parse_mouse(mouse, input[end] == b'm'),
+                None => parse_csi(&params, input[end]),
+            };
+            Some((end + 1, event))
+        },
+        // Alt plus a key, which terminals send as escape followed by the key.
+        _ => {
+            let (consumed, event) = parse_input(&input[1..])?;
+            let event = match event {
+                Input::Key(key) => Input::Key(Key { alt: true, left_alt: true, ..key }),
+                other => other,
+            };
+            Some((consumed + 1, event))
+        },
+    }
+}
+
+// Modified keys come as "number;modifier", where the modifier less one is a
+// bit set with shift as its lowest bit.
+fn parse_csi(params: &str, terminator: u8) -> Input {
+    let mut fields = params.split(';');
+    let number = fields.next().unwrap_or("");
+    let modifier = fields.next().and_then(|field| field.parse::<u32>().ok()).unwrap_or(1);
+    let code = match (terminator, number) {
+        (b'A', _) => KeyCode::Up,
+        (b'B', _) => KeyCode::Down,
+        (b'C', _) => KeyCode::Right,
+        (b'D', _) => KeyCode::Left,
+        (b'H', _) | (b'~', "1") | (b'~', "7") => KeyCode::Home,
+        (b'F', _) | (b'~', "4") | (b'~', "8") => KeyCode::End,
+        (b'~', "2") => KeyCode::Insert,
+        (b'~', "3") => KeyCode::Delete,
+        (b'~', "5") => KeyCode::PageUp,
+        (b'~', "6") => KeyCode::PageDown,
+        _ => return Input::Ignored,
+    };
+    Input::Key(Key { shift: modifier.saturating_sub(1) & 1 != 0, ..key(code) })
+}
+
+fn parse_mouse(params: &str, released: bool) -> Input {
+    let fields: Vec<i32> = params.split(';').filter_map(|field| field.parse().ok()).collect();
+    if fields.len() != 3 {
+        return Input::Ignored;
+    }
+    Input::Mouse { button: fields[0] as u32, x: fields[1] - 1, y: fields[2] - 1, released }
+}
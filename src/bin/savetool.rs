@@ -0,0 +1,11 @@
+extern crate rust_rogue;
+
+use std::process;
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if let Err(error) = rust_rogue::savefile::run_tool(&args) {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
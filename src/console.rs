@@ -0,0 +1,228 @@
+// The wizard console: debugging commands for trying out deep levels and odd
+// situations without playing down to them. Opened with the backtick key.
+
+use std::fs::File;
+use std::io::{ Read, Write };
+
+use tcod::input::KeyCode;
+
+use super::*;
+
+const HISTORY_PATH: &str = "console_history";
+const HISTORY_SIZE: usize = 100;
+
+const HELP: &[&str] = &[
+    "spawn NAME [X Y] - a monster or item, next to you or at X Y",
+    "teleport X Y - move to X Y",
+    "reveal - explore the whole level",
+    "god - toggle god mode",
+    "descend [N] - go down N levels",
+    "set STAT VALUE - hp, max-hp, power, defense, xp, level, mana, max-mana, gold, or an attribute or skill",
+    "dump - log the game state",
+];
+
+pub struct Console {
+    history: Vec<String>,
+}
+
+impl Console {
+    // History is kept between runs; a missing or unreadable file just means
+    // starting with none.
+    pub fn load() -> Console {
+        let mut text = String::new();
+        let _ = File::open(HISTORY_PATH).and_then(|mut file| file.read_to_string(&mut text));
+        Console { history: text.lines().map(String::from).collect() }
+    }
+
+    fn remember(&mut self, command: &str) {
+        if self.history.last().map(String::as_str) != Some(command) {
+            self.history.push(command.to_string());
+        }
+        let excess = self.history.len().saturating_sub(HISTORY_SIZE);
+        self.history.drain(..excess);
+        let _ = File::create(HISTORY_PATH).and_then(|mut file| file.write_all((self.history.join("\n") + "\n").as_bytes()));
+    }
+}
+
+pub fn open(ui: &mut Ui, objects: &mut Vec<Object>, game: &mut Game) {
+    let mut console = match ui.console.take() {
+        Some(console) => console,
+        None => return,
+    };
+
+    let mut text = String::new();
+    let mut history_index = console.history.len();
+    loop {
+        // The prompt goes on the bottom row of the map, just above the panel.
+        let layout = Layout::of(&*ui.renderer);
+        render_all(ui, objects, game);
+        clear_rect(&mut *ui.renderer, 0, layout.panel_y - 1, layout.screen_width, 1);
+        ui.renderer.print(0, layout.panel_y - 1, &format!("` {}_", text), colors::LIGHT_YELLOW);
+        ui.renderer.flush();
+
+        let key = ui.renderer.wait_for_keypress();
+        match key.code {
+            KeyCode::Escape => break,
+            KeyCode::Enter | KeyCode::NumPadEnter => {
+                let command = text.trim().to_string();
+                text.clear();
+                if !command.is_empty() {
+                    console.remember(&command);
+                    game.log.add(format!("` {}", command), colors::LIGHT_GREY);
+                    if let Err(error) = run_command(&command, ui, objects, game) {
+                        game.log.add(error, colors::RED);
+                    }
+                    update_fov(ui, objects, game);
+                }
+                history_index = console.history.len();
+            },
+            KeyCode::Up if history_index > 0 => {
+                history_index -= 1;
+                text = console.history[history_index].clone();
+            },
+            KeyCode::Down if history_index < console.history.len() => {
+                history_index += 1;
+                text = console.history.get(history_index).cloned().unwrap_or_default();
+            },
+            KeyCode::Backspace => {
+                text.pop();
+            },
+            _ if key.printable == '`' => break,
+            _ => {
+                let c = key.printable;
+                if (c.is_ascii_graphic() || c == ' ') && text.len() < layout.screen_width as usize - 3 {
+                    text.push(c);
+                }
+            },
+        }
+    }
+
+    ui.console = Some(console);
+}
+
+fn run_command(command: &str, ui: &mut Ui, objects: &mut Vec<Object>, game: &mut Game) -> Result<(), String> {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    match (words[0], &words[1..]) {
+        ("help", &[]) => {
+            for line in HELP {
+                game.log.add(*line, colors::LIGHT_GREY);
+            }
+        },
+        ("spawn", args) if !args.is_empty() => spawn(args, objects, game)?,
+        ("teleport", &[x, y]) => teleport_player(parse_number(x)?, parse_number(y)?, objects, game)?,
+        ("reveal", &[]) => {
+            reveal_map(game);
+            ui.view.invalidate();
+            game.log.add("The level is revealed.", colors::LIGHT_GREY);
+        },
+        ("god", &[]) => {
+            game.god_mode = !game.god_mode;
+            game.log.add(format!("God mode {}.", if game.god_mode { "on" } else { "off" }), colors::LIGHT_GREY);
+        },
+        ("descend", &[]) => next_level(ui, objects, game),
+        ("descend", &[levels]) => {
+            for _ in 0..parse_number(levels)? {
+                next_level(ui, objects, game);
+            }
+        },
+        ("set", &[stat, value]) => {
+            let value = parse_number(value)?;
+            let player = &mut objects[PLAYER];
+            *stat_mut(stat, player, game).ok_or_else(|| format!("Unknown stat \"{}\".", stat))? = value;
+            game.log.add(format!("{} set to {}.", stat, value), colors::LIGHT_GREY);
+        },
+        ("dump", &[]) => dump(objects, game),
+        _ => return Err(format!("Don't know how to \"{}\". Try \"help\".", command)),
+    }
+    Ok(())
+}
+
+fn parse_number(text: &str) -> Result<i32, String> {
+    text.parse().map_err(|_| format!("Not a number: {}", text))
+}
+
+// Takes the trailing X Y if there is one, otherwise the first free tile
+// around the player.
+fn spawn(args: &[&str], objects: &mut Vec<Object>, game: &mut Game) -> Result<(), String> {
+    let coordinates = if args.len() > 2 {
+        let (x, y) = (&args[args.len() - 2], &args[args.len() - 1]);
+        x.parse().ok().and_then(|x| y.parse().ok().map(|y| (x, y)))
+    } else {
+        None
+    };
+    let name = if coordinates.is_some() { args[..args.len() - 2].join(" ") } else { args.join(" ") };
+
+    let (x, y) = match coordinates {
+        Some((x, y)) if in_map(x, y, &game.map) && !is_blocked(x, y, &game.map, objects) => (x, y),
+        Some((x, y)) => return Err(format!("({}, {}) is blocked.", x, y)),
+        None => free_tile_near_player(objects, game).ok_or("There is no room around you.")?,
+    };
+
+    let object = new_monster(&name, x, y).or_else(|| named_item(&name, x, y)).ok_or_else(|| {
+        format!("Nothing is called \"{}\". Monsters: {}. Items: {}.", name, MONSTERS.join(", "), item_names().join(", "))
+    })?;
+    game.log.add(format!("A {} appears.", object.name), colors::LIGHT_GREY);
+    objects.push(object);
+    Ok(())
+}
+
+fn free_tile_near_player(objects: &[Object], game: &Game) -> Option<(i32, i32)> {
+    let (player_x, player_y) = objects[PLAYER].pos();
+    for radius in 1..cmp::max(map_width(&game.map), map_height(&game.map)) {
+        for dy in -radius..(radius + 1) {
+            for dx in -radius..(radius + 1) {
+                let (x, y) = (player_x + dx, player_y + dy);
+                if in_map(x, y, &game.map) && !is_blocked(x, y, &game.map, objects) {
+                    return Some((x, y));
+                }
+            }
+        }
+    }
+    None
+}
+
+fn stat_mut<'a>(stat: &str, player: &'a mut Object, game: &'a mut Game) -> Option<&'a mut i32> {
+    match stat {
+        "hp" => player.fighter.as_mut().map(|f| &mut f.hp),
+        "max-hp" => player.fighter.as_mut().map(|f| &mut f.base_max_hp),
+        "power" => player.fighter.as_mut().map(|f| &mut f.base_power),
+        "defense" => player.fighter.as_mut().map(|f| &mut f.base_defense),
+        "xp" => player.fighter.as_mut().map(|f| &mut f.xp),
+        "level" => Some(&mut player.level),
+        "mana" => player.caster.as_mut().map(|c| &mut c.mana),
+        "max-mana" => player.caster.as_mut().map(|c| &mut c.max_mana),
+        "gold" => Some(&mut game.gold),
+        _ => {
+            let character = player.character.as_mut()?;
+            match stat {
+                "strength" => Some(&mut character.strength),
+                "dexterity" => Some(&mut character.dexterity),
+                "constitution" => Some(&mut character.constitution),
+                "intelligence" => Some(&mut character.intelligence),
+                "wisdom" => Some(&mut character.wisdom),
+                "weapons" => Some(&mut character.weapons),
+                "magic" => Some(&mut character.magic),
+                "stealth" => Some(&mut character.stealth),
+                _ => None,
+            }
+        },
+    }
+}
+
+fn dump(objects: &[Object], game: &mut Game) {
+    let player = &objects[PLAYER];
+    let hp = player.fighter.map_or(0, |f| f.hp);
+    let xp = player.fighter.map_or(0, |f| f.xp);
+    let lines = vec![
+        format!("Dungeon level {}, position ({}, {}), god mode {}.", game.dungeon_level, player.x, player.y,
+                if game.god_mode { "on" } else { "off" }),
+        format!("HP {}/{}, power {}, defense {}, level {}, XP {}, gold {}.", hp, player.max_hp(game),
+                player.power(game), player.defense(game), player.level, xp, game.gold),
+        format!("{} objects, {} monsters, {} items on the floor, {} in the inventory.", objects.len(),
+                objects.iter().filter(|o| o.ai.is_some()).count(),
+                objects.iter().filter(|o| o.item.is_some()).count(), game.inventory.len()),
+    ];
+    for line in lines {
+        game.log.add(line, colors::LIGHT_GREY);
+    }
+}
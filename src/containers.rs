@@ -0,0 +1,182 @@
+// Chests and bags. Whatever a container holds is kept in its own `inventory`,
+// so it is saved along with it, however deep it is nested: a bag can lie in a
+// chest, though not in another bag.
+//
+// Chests stand in rooms and are opened by walking into them. Some are locked,
+// and the lock has to be picked first; some are trapped, and the trap goes off
+// the first time they are opened. Bags are carried, take up a single slot of
+// the inventory whatever they hold, and are opened by using them.
+
+use super::*;
+
+const CHEST_CHANCE: u32 = 15;
+const CHEST_CAPACITY: usize = 20;
+pub const BAG_CAPACITY: usize = 10;
+const MAX_CHEST_ITEMS: u32 = 3;
+const LOCKED_CHANCE: u32 = 30;
+const LOCKPICK_CHANCE: u32 = 30;
+const TRAP_RADIUS: i32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Container {
+    pub capacity: usize,
+    pub locked: bool,
+    pub trap: Option<Trap>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Trap {
+    Gas,
+    Fire,
+}
+
+pub fn new_chest(level: u32, x: i32, y: i32) -> Object {
+    let mut chest = Object::new(x, y, '&', "chest", colors::DARK_ORANGE, true);
+    chest.always_visible = true;
+    let trap_chance = from_dungeon_level(&[Transition { level: 2, value: 20 }], level);
+    let trap = if rng().gen_range(0, 100) < trap_chance {
+        Some(if rng().gen() { Trap::Gas } else { Trap::Fire })
+    } else {
+        None
+    };
+    chest.container = Some(Container {
+        capacity: CHEST_CAPACITY,
+        locked: rng().gen_range(0, 100) < LOCKED_CHANCE,
+        trap,
+    });
+
+    let chances = &mut item_chances(level);
+    let item_choice = WeightedChoice::new(chances);
+    for _ in 0..rng().gen_range(1, MAX_CHEST_ITEMS + 1) {
+        chest.inventory.push(generate_item(item_choice.ind_sample(&mut rng()), level, x, y));
+    }
+    if rng().gen_range(0, 100) < GOLD_CHANCE {
+        chest.gold = rng().gen_range(10, 20 + 10 * level as i32);
+    }
+    chest
+}
+
+// Now and then a chest, somewhere out of the way: never on the room's center,
+// where the player or the stairs may be.
+pub fn place_chest(level: u32, room: Rect, map: &Map, objects: &mut Vec<Object>) {
+    if rng().gen_range(0, 100) >= CHEST_CHANCE {
+        return;
+    }
+    let x = rng().gen_range(room.x1 + 1, room.x2);
+    let y = rng().gen_range(room.y1 + 1, room.y2);
+    if (x, y) != room.center() && !is_blocked(x, y, map, objects) {
+        objects.push(new_chest(level, x, y));
+    }
+}
+
+// What walking into a chest does: try the lock if there is one, otherwise
+// spring any trap and look inside.
+pub fn open_chest(chest_id: usize, objects: &mut [Object], game: &mut Game, ui: &mut Ui) {
+    let container = match objects[chest_id].container {
+        Some(container) => container,
+        None => return,
+    };
+    if container.locked {
+        let pick_bonus = objects[PLAYER].character.as_ref().map_or(0, |c| c.steal_bonus());
+        if rng().gen_range(0, 100) >= LOCKPICK_CHANCE + pick_bonus {
+            game.log.add("The chest is locked. You fail to pick the lock.", colors::LIGHT_GREY);
+            return;
+        }
+        game.log.add("You pick the lock.", colors::LIGHT_GREEN);
+    }
+    if let Some(trap) = container.trap {
+        spring_trap(trap, objects[chest_id].pos(), game);
+    }
+    objects[chest_id].container = Some(Container { locked: false, trap: None, ..container });
+
+    let gold = objects[chest_id].gold;
+    if gold > 0 {
+        objects[chest_id].gold = 0;
+        game.gold += gold;
+        game.log.add(format!("You find {} gold pieces in the chest.", gold), colors::GOLD);
+    }
+    rummage(&mut objects[chest_id], INVENTORY_SIZE, game, ui);
+}
+
+fn spring_trap(trap: Trap, pos: (i32, i32), game: &mut Game) {
+    match trap {
+        Trap::Gas => {
+            game.log.add("A cloud of poison gas bursts out of the chest!", colors::LIGHT_GREEN);
+            effects::release_gas(&mut game.map, pos, TRAP_RADIUS);
+        },
+        Trap::Fire => {
+            game.log.add("Flames burst out of the chest!", colors::ORANGE);
+            effects::ignite_area(&mut game.map, pos, TRAP_RADIUS);
+        },
+    }
+}
+
+// Using a bag opens it. It is taken out of the inventory while it is open, so
+// that it can't be put inside itself, and goes back in the same place after.
+pub fn open_bag(inventory_id: usize, _objects: &mut [Object], game: &mut Game, ui: &mut Ui) -> UseResult {
+    let mut bag = game.inventory.remove(inventory_id);
+    rummage(&mut bag, INVENTORY_SIZE - 1, game, ui);
+    game.inventory.insert(inventory_id, bag);
+    UseResult::UsedAndKept
+}
+
+// A menu of what the container holds, to take things out of it, with one more
+// option to put something in, until the player closes it. `inventory_size` is
+// how many items the inventory can take while the container is open.
+fn rummage(container: &mut Object, inventory_size: usize, game: &mut Game, ui: &mut Ui) {
+    loop {
+        let header = format!("The {} holds {} of {} items. Press the key next to an item to take it out, or any other to close it.\n",
+                             container.name, container.inventory.len(), capacity(container));
+        let mut options: Vec<String> = container.inventory.iter().map(|item| item_name(item, game)).collect();
+        options.push("(put something in)".to_string());
+        let width = Layout::of(&*ui.renderer).inventory_width;
+        match menu(&header, &options, width, &mut *ui.renderer) {
+            Some(index) if index < container.inventory.len() => take_out(container, index, inventory_size, game),
+            Some(_) => {
+                let header = format!("Press the key next to an item to put it in the {}, or any other to cancel.\n", container.name);
+                if let Some(inventory_id) = inventory_menu(&game.inventory, game, &header, &mut *ui.renderer) {
+                    put_in(container, inventory_id, game);
+                }
+            },
+            None => return,
+        }
+    }
+}
+
+fn capacity(container: &Object) -> usize {
+    container.container.map_or(0, |c| c.capacity)
+}
+
+fn take_out(container: &mut Object, index: usize, inventory_size: usize, game: &mut Game) {
+    let name = item_name(&container.inventory[index], game);
+    if game.inventory.len() >= inventory_size {
+        game.log.add(format!("Your inventory is full, cannot take the {}.", name), colors::RED);
+    } else {
+        game.inventory.push(container.inventory.remove(index));
+        game.log.add(format!("You take the {} out of the {}.", name, container.name), colors::GREEN);
+    }
+}
+
+fn put_in(container: &mut Object, inventory_id: usize, game: &mut Game) {
+    let name = item_name(&game.inventory[inventory_id], game);
+    if enchantment::is_stuck(inventory_id, game) {
+        return;
+    }
+    if container.item.is_some() && game.inventory[inventory_id].container.is_some() {
+        game.log.add(format!("The {} won't fit inside the {}.", name, container.name), colors::RED);
+    } else if container.inventory.len() >= capacity(container) {
+        game.log.add(format!("The {} is full.", container.name), colors::RED);
+    } else {
+        let mut item = game.inventory.remove(inventory_id);
+        if item.equipment.is_some() {
+            item.dequip(&mut game.log);
+        }
+        container.inventory.push(item);
+        game.log.add(format!("You put the {} in the {}.", name, container.name), colors::GREEN);
+    }
+}
+
+// What everything in a container is worth.
+pub fn contents_value(container: &Object) -> i32 {
+    container.inventory.iter().map(item_value).sum()
+}
@@ -0,0 +1,59 @@
+// What the look command says about things, kept together here so the text is
+// easy to find and change. Descriptions are looked up by the name the player
+// knows a thing by, so unidentified potions and scrolls give nothing away.
+
+const DESCRIPTIONS: &[(&str, &str)] = &[
+    ("orc", "A brutish warrior of the deep tunnels. Alone it is little threat; orcs are rarely alone."),
+    ("troll", "Huge, green and hungry. Trolls hit hard and take a lot of killing."),
+    ("ogre", "A hulking giant that can fell an unwary adventurer in a couple of blows."),
+    ("shopkeeper", "A merchant who has made a home in the tomb. Peaceful, unless given a reason not to be."),
+    ("healing potion", "Closes wounds when drunk."),
+    ("scroll of lightning bolt", "Calls lightning down on the nearest enemy."),
+    ("scroll of fireball", "Hurls a ball of fire that bursts over a wide area. Mind you're not in it."),
+    ("scroll of confusion", "Addles the wits of an enemy, who stumbles around for a while."),
+    ("scroll of poison cloud", "Lets loose a cloud of poison gas that drifts and thins over a few turns."),
+    ("scroll of identify", "Reveals the true nature of an unknown item."),
+    ("scroll of enchant equipment", "Makes a weapon or piece of armor a little better, for good."),
+    ("scroll of remove curse", "Lifts the curses from everything you carry."),
+    ("sword", "A well balanced blade, held in the right hand."),
+    ("dagger", "A short blade for the off hand."),
+    ("shield", "A battered but sturdy shield, held in the left hand."),
+    ("pickaxe", "A miner's pick, held in the right hand. Walk into rock with it to dig. It makes a poor weapon."),
+    ("lantern", "A brass lantern, hung from the belt. It lights the way further than a torch, while its oil lasts."),
+    ("repair kit", "Tools, rivets and a whetstone, enough to mend one weapon or piece of armor as good as new."),
+    ("anvil", "A smith's anvil, left behind. Walk into it to hammer everything you carry back into shape, once."),
+    ("cracked anvil", "A smith's anvil, cracked through. It is no more use to anyone."),
+    ("bag", "A leather sack that holds a good few items, and takes up one place in your pack. Use it to open it."),
+    ("chest", "A heavy wooden chest. Walk into it to open it, if it isn't locked, or trapped."),
+    ("wall torch", "A torch in an iron bracket, still burning. Someone keeps these lit."),
+    ("stairs", "Stairs leading further down into the tomb."),
+    ("wall", "Ancient stone, cold and damp."),
+    ("floor", "Dusty flagstones, worn smooth by long-dead feet."),
+    ("cracked wall", "Stone split by age. A good blast would bring it down."),
+    ("partly dug wall", "Rock that someone has taken a pick to. A few more blows would break through."),
+    ("closed door", "Heavy old timbers. Walk into it to open it."),
+    ("open door", "Heavy old timbers, standing open."),
+    ("dry brush", "Dead, brittle growth. It would go up like tinder."),
+    ("fire", "Flames that burn anything standing in them. They spread over dry brush."),
+    ("poison gas", "A choking green haze, harmful to breathe. It drifts and thins as it goes."),
+    ("water", "Cold, dark water. Fire won't burn on it, and gas won't settle over it."),
+];
+
+// Names with a part that varies, matched by the part that doesn't.
+const PREFIXES: &[(&str, &str)] = &[
+    ("scroll labeled ", "A scroll whose writing means nothing to you. Reading it is one way to find out."),
+    ("spellbook of ", "Studying it teaches the spell within."),
+    ("remains of ", "What is left of something that crossed the wrong adventurer."),
+];
+
+const SUFFIXES: &[(&str, &str)] = &[
+    (" potion", "A potion you haven't identified. Drinking it is one way to find out."),
+    (" gold pieces", "Coins of some long-fallen kingdom. Shopkeepers still take them."),
+];
+
+pub fn describe(name: &str) -> Option<&'static str> {
+    let exact = DESCRIPTIONS.iter().find(|&&(key, _)| key == name);
+    let prefix = PREFIXES.iter().find(|&&(key, _)| name.starts_with(key));
+    let suffix = SUFFIXES.iter().find(|&&(key, _)| name.ends_with(key));
+    exact.or(prefix).or(suffix).map(|&(_, description)| description)
+}
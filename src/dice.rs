@@ -0,0 +1,25 @@
+use std::cell::RefCell;
+
+use rand::{ self, Rng, SeedableRng, XorShiftRng };
+
+thread_local!(static RNG: RefCell<XorShiftRng> = RefCell::new(rand::thread_rng().gen()));
+
+// Everything random in the game draws from this generator, so seeding it
+// replays the same dungeon, monsters and fights.
+pub struct GameRng;
+
+impl Rng for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        RNG.with(|rng| rng.borrow_mut().next_u32())
+    }
+}
+
+pub fn rng() -> GameRng {
+    GameRng
+}
+
+pub fn seed(seed: u32) {
+    // XorShift must not be seeded with all zeroes.
+    let state = [seed, seed ^ 0x9e37_79b9, 0x2545_f491, 0x6c07_8965];
+    RNG.with(|rng| *rng.borrow_mut() = XorShiftRng::from_seed(state));
+}
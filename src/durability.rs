@@ -0,0 +1,181 @@
+// Wear and tear on weapons and armor. Weapons wear with every blow they land
+// (a pickaxe with every blow at the rock, too), and armor with every blow that
+// gets through it. Past half its durability an item only gives half its
+// bonuses, and at none it breaks and is gone.
+//
+// Repair kits mend one item; anvils, found here and there deeper down, mend
+// everything the player carries, once.
+
+use super::*;
+
+pub const WEAPON_DURABILITY: i32 = 80;
+pub const ARMOR_DURABILITY: i32 = 60;
+pub const PICKAXE_DURABILITY: i32 = 40;
+const ANVIL_CHANCE: u32 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Anvil {
+    Whole,
+    // Used once, and no more use to anyone.
+    Cracked,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Durability {
+    pub current: i32,
+    pub max: i32,
+}
+
+impl Durability {
+    pub fn new(max: i32) -> Durability {
+        Durability { current: max, max }
+    }
+
+    pub fn is_damaged(self) -> bool {
+        self.current * 2 <= self.max
+    }
+
+    pub fn condition(self) -> &'static str {
+        if self.current == self.max {
+            "perfect"
+        } else if self.current * 4 > self.max * 3 {
+            "good"
+        } else if !self.is_damaged() {
+            "worn"
+        } else if self.current * 4 > self.max {
+            "damaged"
+        } else {
+            "nearly broken"
+        }
+    }
+}
+
+// The condition worth pointing out in a list of items: anything but new.
+pub fn condition_note(object: &Object) -> Option<&'static str> {
+    object.durability.filter(|d| d.current < d.max).map(|d| d.condition())
+}
+
+// The bonuses an item gives as it is: halved, where they help, once it is
+// damaged.
+pub fn effective(object: &Object) -> Option<Equipment> {
+    let mut equipment = object.equipment?;
+    if object.durability.map_or(false, |d| d.is_damaged()) {
+        let halve = |bonus: i32| if bonus > 0 { bonus / 2 } else { bonus };
+        equipment.power_bonus = halve(equipment.power_bonus);
+        equipment.defense_bonus = halve(equipment.defense_bonus);
+        equipment.max_hp_bonus = halve(equipment.max_hp_bonus);
+    }
+    Some(equipment)
+}
+
+// What takes the wear: the weapons when they land a blow, the armor when a
+// blow gets through it, and the pickaxe alone when it is swung at the rock.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Wear {
+    Weapons,
+    Armor,
+    Pickaxe,
+}
+
+fn takes_wear(item: &Object, wear: Wear) -> bool {
+    let equipment = match item.equipment {
+        Some(equipment) if equipment.equipped => equipment,
+        _ => return false,
+    };
+    match wear {
+        Wear::Weapons => enchantment::is_weapon(&equipment),
+        Wear::Armor => !enchantment::is_weapon(&equipment),
+        Wear::Pickaxe => item.item == Some(Item::Pickaxe),
+    }
+}
+
+// A point of wear on some of the player's equipment, breaking anything that
+// has none left. Either can take away hit points the item gave, so the player
+// is left with no more than the new maximum.
+pub fn wear(player: &mut Object, wear: Wear, game: &mut Game) {
+    let mut broken = vec![];
+    for (id, item) in game.inventory.iter_mut().enumerate() {
+        let worn = takes_wear(item, wear);
+        if let (true, Some(durability)) = (worn, item.durability.as_mut()) {
+            durability.current -= 1;
+            if durability.current <= 0 {
+                broken.push(id);
+            }
+        }
+    }
+    for id in broken.into_iter().rev() {
+        let item = game.inventory.remove(id);
+        let name = item_name(&item, game);
+        game.log.add(format!("Your {} breaks!", name), colors::RED);
+    }
+    let max_hp = player.max_hp(game);
+    if let Some(ref mut fighter) = player.fighter {
+        fighter.hp = cmp::min(fighter.hp, max_hp);
+    }
+}
+
+fn repair(object: &mut Object) {
+    if let Some(ref mut durability) = object.durability {
+        durability.current = durability.max;
+    }
+}
+
+// A repair kit mends a chosen item as good as new.
+pub fn use_repair_kit(_inventory_id: usize, _objects: &mut [Object], game: &mut Game, ui: &mut Ui) -> UseResult {
+    let damaged: Vec<usize> = (0..game.inventory.len()).filter(|&id| condition_note(&game.inventory[id]).is_some()).collect();
+    if damaged.is_empty() {
+        game.log.add("Nothing you carry needs mending.", colors::LIGHT_GREY);
+        return UseResult::Cancelled;
+    }
+    let options: Vec<String> = damaged.iter().map(|&id| {
+        let item = &game.inventory[id];
+        format!("{} ({})", item_name(item, game), condition_note(item).unwrap())
+    }).collect();
+    match menu("Choose an item to repair:\n", &options, Layout::of(&*ui.renderer).inventory_width, &mut *ui.renderer) {
+        Some(choice) => {
+            repair(&mut game.inventory[damaged[choice]]);
+            let name = item_name(&game.inventory[damaged[choice]], game);
+            game.log.add(format!("You mend your {}.", name), colors::LIGHT_GREEN);
+            UseResult::UsedUp
+        },
+        None => UseResult::Cancelled,
+    }
+}
+
+pub fn new_anvil(x: i32, y: i32) -> Object {
+    let mut anvil = Object::new(x, y, '=', "anvil", colors::LIGHT_GREY, true);
+    anvil.always_visible = true;
+    anvil.anvil = Some(Anvil::Whole);
+    anvil
+}
+
+// Anvils stand out of the way, like chests: never on a room's center.
+pub fn place_anvil(level: u32, room: Rect, map: &Map, objects: &mut Vec<Object>) {
+    if level < 2 || rng().gen_range(0, 100) >= ANVIL_CHANCE {
+        return;
+    }
+    let x = rng().gen_range(room.x1 + 1, room.x2);
+    let y = rng().gen_range(room.y1 + 1, room.y2);
+    if (x, y) != room.center() && !is_blocked(x, y, map, objects) {
+        objects.push(new_anvil(x, y));
+    }
+}
+
+// Walking into an anvil mends everything carried, after which it is spent.
+pub fn use_anvil(anvil_id: usize, objects: &mut [Object], game: &mut Game) {
+    if objects[anvil_id].anvil != Some(Anvil::Whole) {
+        game.log.add("The anvil is cracked through. It is no more use to anyone.", colors::LIGHT_GREY);
+        return;
+    }
+    if !game.inventory.iter().any(|item| condition_note(item).is_some()) {
+        game.log.add("Nothing you carry needs mending.", colors::LIGHT_GREY);
+        return;
+    }
+    for item in game.inventory.iter_mut() {
+        repair(item);
+    }
+    objects[anvil_id].anvil = Some(Anvil::Cracked);
+    objects[anvil_id].name = "cracked anvil".to_string();
+    objects[anvil_id].color = colors::DARK_GREY;
+    game.log.add("You hammer your gear back into shape, and the anvil cracks under the last blow.", colors::LIGHT_GREEN);
+}
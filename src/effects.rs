@@ -0,0 +1,227 @@
+// Area effects: fire, poison gas and water lying on the map, changing a little
+// every turn. Fire burns out after a while but spreads over dry brush, gas
+// drifts out from where it was let loose and thins away, and water spreads
+// out until it settles. None of them get through walls, and neither does the
+// blast of a fireball.
+
+use tcod::line::Line;
+
+use super::*;
+
+const FIRE_DAMAGE: i32 = 4;
+const GAS_DAMAGE: i32 = 2;
+// Turns a fire burns on bare stone, and on dry brush.
+const FIRE_TURNS: i32 = 2;
+const BRUSH_FIRE_TURNS: i32 = 6;
+// Chance in a hundred, each turn, that fire catches on a patch of brush next
+// to it.
+const SPREAD_CHANCE: u32 = 40;
+pub const POISON_DENSITY: i32 = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Effect {
+    Fire { turns: i32 },
+    // Thinner every turn, and gone at 0.
+    Gas { density: i32 },
+    // Water runs from a tile to a neighbour at least two shallower.
+    Water { depth: i32 },
+}
+
+impl Effect {
+    pub fn name(self) -> &'static str {
+        match self {
+            Effect::Fire { .. } => "fire",
+            Effect::Gas { .. } => "poison gas",
+            Effect::Water { .. } => "water",
+        }
+    }
+}
+
+// Whether nothing solid stands between the two tiles, for blasts and clouds.
+pub fn line_of_effect(map: &Map, from: (i32, i32), to: (i32, i32)) -> bool {
+    Line::new(from, to).take_while(|&tile| tile != to).all(|(x, y)| !map[x as usize][y as usize].block_sight)
+}
+
+// The open tiles within a radius of a point that it can reach.
+fn area(map: &Map, (center_x, center_y): (i32, i32), radius: i32) -> Vec<(i32, i32)> {
+    let mut tiles = vec![];
+    for y in center_y - radius..center_y + radius + 1 {
+        for x in center_x - radius..center_x + radius + 1 {
+            let (dx, dy) = (x - center_x, y - center_y);
+            if in_map(x, y, map) && !map[x as usize][y as usize].blocked
+                && ((dx * dx + dy * dy) as f32).sqrt() <= radius as f32
+                && line_of_effect(map, (center_x, center_y), (x, y)) {
+                tiles.push((x, y));
+            }
+        }
+    }
+    tiles
+}
+
+// Sets the ground alight, unless it is under water. Brush burns longer, and
+// only once.
+pub fn ignite(map: &mut Map, x: i32, y: i32) {
+    let tile = &mut map[x as usize][y as usize];
+    if tile.blocked || matches!(tile.effect, Some(Effect::Water { .. })) {
+        return;
+    }
+    tile.effect = Some(Effect::Fire { turns: if tile.flammable { BRUSH_FIRE_TURNS } else { FIRE_TURNS } });
+    tile.flammable = false;
+}
+
+pub fn ignite_area(map: &mut Map, center: (i32, i32), radius: i32) {
+    for (x, y) in area(map, center, radius) {
+        ignite(map, x, y);
+    }
+}
+
+// Gas fills the open tiles around a point, except where there is water.
+pub fn release_gas(map: &mut Map, center: (i32, i32), radius: i32) {
+    for (x, y) in area(map, center, radius) {
+        let tile = &mut map[x as usize][y as usize];
+        if !matches!(tile.effect, Some(Effect::Water { .. })) {
+            tile.effect = Some(Effect::Gas { density: POISON_DENSITY });
+        }
+    }
+}
+
+// A turn of the effects on the map: they hurt whatever stands in them first,
+// then burn, drift and flow.
+pub fn update(objects: &mut Vec<Object>, game: &mut Game, fov: &Vision) {
+    harm(objects, game, fov);
+    burn(&mut game.map);
+    drift(&mut game.map);
+    flow(&mut game.map);
+}
+
+fn harm(objects: &mut Vec<Object>, game: &mut Game, fov: &Vision) {
+    for object in objects.iter_mut() {
+        let (x, y) = object.pos();
+        let (damage, verb) = match game.map[x as usize][y as usize].effect {
+            Some(Effect::Fire { .. }) => (FIRE_DAMAGE, "burned"),
+            Some(Effect::Gas { .. }) => (GAS_DAMAGE, "choking on the gas"),
+            _ => continue,
+        };
+        if object.fighter.is_none() || !object.alive || (object.is_player() && game.god_mode) {
+            continue;
+        }
+        if object.is_player() {
+            game.log.add(format!("You are {}, losing {} hit points.", verb, damage), colors::ORANGE);
+        } else if fov.is_in_fov(x, y) {
+            game.log.add(format!("The {} is {}, losing {} hit points.", object.name, verb, damage), colors::ORANGE);
+        }
+        object.take_damage(damage, &mut game.log);
+    }
+
+    // Paper burns.
+    let burnt: Vec<usize> = (0..objects.len()).rev().filter(|&id| {
+        let object = &objects[id];
+        matches!(object.item, Some(item) if UNIDENTIFIED_SCROLLS.contains(&item) || matches!(item, Item::Spellbook(_)))
+            && matches!(game.map[object.x as usize][object.y as usize].effect, Some(Effect::Fire { .. }))
+    }).collect();
+    for id in burnt {
+        let object = objects.remove(id);
+        if fov.is_in_fov(object.x, object.y) {
+            let name = item_name(&object, game);
+            game.log.add(format!("The {} goes up in flames.", name), colors::ORANGE);
+        }
+    }
+}
+
+fn burn(map: &mut Map) {
+    let mut catching = vec![];
+    for x in 0..map_width(map) {
+        for y in 0..map_height(map) {
+            if let Some(Effect::Fire { turns }) = map[x as usize][y as usize].effect {
+                map[x as usize][y as usize].effect = if turns > 1 { Some(Effect::Fire { turns: turns - 1 }) } else { None };
+                catching.extend(pathfinding::neighbours(x, y, map).into_iter().filter(|&(nx, ny)| {
+                    let tile = &map[nx as usize][ny as usize];
+                    tile.flammable && tile.effect.is_none()
+                }));
+            }
+        }
+    }
+    for (x, y) in catching {
+        if map[x as usize][y as usize].flammable && rng().gen_range(0, 100) < SPREAD_CHANCE {
+            ignite(map, x, y);
+        }
+    }
+}
+
+// Gas thins where it is and spreads a little thinner to the tiles around it.
+fn drift(map: &mut Map) {
+    let (width, height) = (map_width(map), map_height(map));
+    let density = |map: &Map, x: i32, y: i32| match map[x as usize][y as usize].effect {
+        Some(Effect::Gas { density }) => density,
+        _ => 0,
+    };
+    if !map.iter().flat_map(|column| column.iter()).any(|tile| matches!(tile.effect, Some(Effect::Gas { .. }))) {
+        return;
+    }
+    let mut densities = vec![0; (width * height) as usize];
+    for x in 0..width {
+        for y in 0..height {
+            let here = density(map, x, y);
+            let around = pathfinding::neighbours(x, y, map).into_iter().map(|(nx, ny)| density(map, nx, ny)).max().unwrap_or(0);
+            densities[(y * width + x) as usize] = cmp::max(here - 1, around - 2);
+        }
+    }
+    for x in 0..width {
+        for y in 0..height {
+            let tile = &mut map[x as usize][y as usize];
+            if tile.blocked || !matches!(tile.effect, None | Some(Effect::Gas { .. })) {
+                continue;
+            }
+            let density = densities[(y * width + x) as usize];
+            tile.effect = if density > 0 { Some(Effect::Gas { density }) } else { None };
+        }
+    }
+}
+
+// Each tile of water spills a little into its shallowest neighbour, if that is
+// at least two shallower. Water puts out fire and pushes gas aside.
+fn flow(map: &mut Map) {
+    let depth = |map: &Map, x: i32, y: i32| match map[x as usize][y as usize].effect {
+        Some(Effect::Water { depth }) => depth,
+        _ => 0,
+    };
+    let mut spills = vec![];
+    for x in 0..map_width(map) {
+        for y in 0..map_height(map) {
+            let here = depth(map, x, y);
+            if here < 2 {
+                continue;
+            }
+            let lowest = pathfinding::neighbours(x, y, map).into_iter()
+                .filter(|&(nx, ny)| !map[nx as usize][ny as usize].blocked)
+                .min_by_key(|&(nx, ny)| depth(map, nx, ny));
+            if let Some((nx, ny)) = lowest {
+                if depth(map, nx, ny) <= here - 2 {
+                    spills.push(((x, y), (nx, ny)));
+                }
+            }
+        }
+    }
+    for ((x, y), (nx, ny)) in spills {
+        let from = depth(map, x, y);
+        let to = depth(map, nx, ny);
+        map[x as usize][y as usize].effect = Some(Effect::Water { depth: from - 1 });
+        map[nx as usize][ny as usize].effect = Some(Effect::Water { depth: to + 1 });
+    }
+}
+
+// Some rooms are overgrown with dry brush, and deeper down some are flooded.
+pub fn furnish_room(level: u32, room: Rect, map: &mut Map) {
+    let flood_chance = from_dungeon_level(&[Transition { level: 2, value: 10 }], level);
+    let roll = rng().gen_range(0, 100);
+    for x in room.x1 + 1..room.x2 {
+        for y in room.y1 + 1..room.y2 {
+            let tile = &mut map[x as usize][y as usize];
+            if roll < flood_chance {
+                tile.effect = Some(Effect::Water { depth: 2 });
+            } else if roll < flood_chance + 20 {
+                tile.flammable = true;
+            }
+        }
+    }
+}
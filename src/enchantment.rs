@@ -0,0 +1,350 @@
+// Quality, enchantments, affixes and curses on equipment found in the dungeon.
+//
+// Quality and enchantment change an item's main bonus: attack for weapons,
+// defense for everything else. Some affixes add to a bonus too, and the others
+// do something in combat. All of it is added into the item's `Equipment`
+// bonuses when the item is made, so that the rest of the game doesn't need to
+// know about it; the `Enchantment` is kept beside them to name the item and to
+// take the magic back out of what the player is shown until they identify it.
+//
+// Quality is plain to see. Magic isn't, and neither is a curse until the item
+// is put on, after which it can't be taken off until the curse is lifted.
+
+use super::*;
+
+// Chance in a hundred of an affix in each place, prefix and suffix.
+const AFFIX_CHANCE: u32 = 30;
+// Chance in a hundred that a magic item is cursed.
+const CURSE_CHANCE: u32 = 25;
+const MAX_ENCHANTMENT: i32 = 3;
+const STURDY_HP: i32 = 10;
+const PROTECTION_DEFENSE: i32 = 2;
+const FIRE_DAMAGE: i32 = 3;
+const THORNS_DAMAGE: i32 = 2;
+// A vampiric weapon gives back this fraction of the damage it does.
+const VAMPIRIC_DIVISOR: i32 = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Enchantment {
+    pub quality: Quality,
+    // The +N (or -N, for cursed items).
+    pub bonus: i32,
+    pub prefix: Option<Affix>,
+    pub suffix: Option<Affix>,
+    pub cursed: bool,
+    pub identified: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Quality {
+    Crude,
+    Common,
+    Fine,
+    Masterwork,
+}
+
+impl Quality {
+    fn modifier(self) -> i32 {
+        match self {
+            Quality::Crude => -1,
+            Quality::Common => 0,
+            Quality::Fine => 1,
+            Quality::Masterwork => 2,
+        }
+    }
+
+    fn name(self) -> Option<&'static str> {
+        match self {
+            Quality::Crude => Some("crude"),
+            Quality::Common => None,
+            Quality::Fine => Some("fine"),
+            Quality::Masterwork => Some("masterwork"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub enum Affix {
+    // Weapons: heals the wielder for part of the damage done.
+    Vampiric,
+    // Weapons: burns whatever it hits.
+    Fire,
+    // Anything else: more hit points.
+    Sturdy,
+    // Anything else: more defense.
+    Protection,
+    // Anything else: hurts whatever lands a blow on the wearer.
+    Thorns,
+}
+
+impl Affix {
+    pub fn name(self) -> &'static str {
+        match self {
+            Affix::Vampiric => "vampiric",
+            Affix::Sturdy => "sturdy",
+            Affix::Fire => "of fire",
+            Affix::Protection => "of protection",
+            Affix::Thorns => "of thorns",
+        }
+    }
+
+    pub fn description(self) -> String {
+        match self {
+            Affix::Vampiric => format!("Heals you for a {}th of the damage it does", VAMPIRIC_DIVISOR),
+            Affix::Fire => format!("Burns what it hits for {} more damage", FIRE_DAMAGE),
+            Affix::Sturdy => format!("{:+} max HP", STURDY_HP),
+            Affix::Protection => format!("{:+} defense", PROTECTION_DEFENSE),
+            Affix::Thorns => format!("Hurts anything that hits you for {} damage", THORNS_DAMAGE),
+        }
+    }
+}
+
+pub fn is_weapon(equipment: &Equipment) -> bool {
+    equipment.slot == Slot::RightHand || equipment.power_bonus > 0
+}
+
+// Changes the bonus that quality and enchantment go to.
+fn adjust(equipment: &mut Equipment, amount: i32) {
+    if is_weapon(equipment) {
+        equipment.power_bonus += amount;
+    } else {
+        equipment.defense_bonus += amount;
+    }
+}
+
+// What each affix adds to the item's bonuses, or takes away with -1.
+fn apply_affix(affix: Affix, equipment: &mut Equipment, sign: i32) {
+    match affix {
+        Affix::Sturdy => equipment.max_hp_bonus += sign * STURDY_HP,
+        Affix::Protection => equipment.defense_bonus += sign * PROTECTION_DEFENSE,
+        _ => {},
+    }
+}
+
+// Rolls the quality and any magic of a piece of equipment made on a dungeon
+// level. Anything else is left as it is.
+pub fn roll(object: &mut Object, level: u32) {
+    let equipment = match object.equipment {
+        Some(equipment) => equipment,
+        None => return,
+    };
+
+    let qualities = &mut [
+        Weighted { weight: 20, item: Quality::Crude },
+        Weighted { weight: 60, item: Quality::Common },
+        Weighted { weight: from_dungeon_level(&[Transition { level: 2, value: 20 }, Transition { level: 5, value: 30 }], level), item: Quality::Fine },
+        Weighted { weight: from_dungeon_level(&[Transition { level: 4, value: 5 }, Transition { level: 7, value: 15 }], level), item: Quality::Masterwork },
+    ];
+    let quality = WeightedChoice::new(qualities).ind_sample(&mut rng());
+    let mut enchantment = Enchantment { quality, bonus: 0, prefix: None, suffix: None, cursed: false, identified: true };
+
+    let magic_chance = from_dungeon_level(&[
+        Transition { level: 1, value: 10 },
+        Transition { level: 3, value: 20 },
+        Transition { level: 6, value: 30 },
+    ], level);
+    if rng().gen_range(0, 100) < magic_chance {
+        enchantment.identified = false;
+        let strength = cmp::min(MAX_ENCHANTMENT, 1 + level as i32 / 3);
+        if rng().gen_range(0, 100) < CURSE_CHANCE {
+            enchantment.cursed = true;
+            enchantment.bonus = -rng().gen_range(1, strength + 1);
+        } else {
+            enchantment.bonus = rng().gen_range(0, strength + 1);
+            let (prefix, suffix) = if is_weapon(&equipment) {
+                (Affix::Vampiric, Affix::Fire)
+            } else {
+                (Affix::Sturdy, *rng().choose(&[Affix::Protection, Affix::Thorns]).unwrap())
+            };
+            if rng().gen_range(0, 100) < AFFIX_CHANCE {
+                enchantment.prefix = Some(prefix);
+            }
+            if rng().gen_range(0, 100) < AFFIX_CHANCE {
+                enchantment.suffix = Some(suffix);
+            }
+        }
+    }
+
+    apply(object, enchantment);
+}
+
+// Gives a plain piece of equipment its quality and magic.
+pub fn apply(object: &mut Object, enchantment: Enchantment) {
+    if let Some(ref mut equipment) = object.equipment {
+        adjust(equipment, enchantment.quality.modifier() + enchantment.bonus);
+        for affix in enchantment.prefix.iter().chain(&enchantment.suffix) {
+            apply_affix(*affix, equipment, 1);
+        }
+    }
+    object.enchantment = Some(enchantment);
+}
+
+// The whole name, magic and all, like "cursed crude shield -2" or "fine
+// vampiric sword of fire +1".
+pub fn full_name(object: &Object) -> String {
+    let enchantment = match object.enchantment {
+        Some(enchantment) => enchantment,
+        None => return object.name.clone(),
+    };
+    let mut words = vec![];
+    if enchantment.cursed {
+        words.push("cursed".to_string());
+    }
+    words.extend(enchantment.quality.name().map(String::from));
+    words.extend(enchantment.prefix.map(|affix| affix.name().to_string()));
+    words.push(object.name.clone());
+    words.extend(enchantment.suffix.map(|affix| affix.name().to_string()));
+    if enchantment.bonus != 0 {
+        words.push(format!("{:+}", enchantment.bonus));
+    }
+    words.join(" ")
+}
+
+// The name the player knows: just the quality, until the item is identified.
+pub fn known_name(object: &Object) -> String {
+    match object.enchantment {
+        Some(enchantment) if !enchantment.identified => match enchantment.quality.name() {
+            Some(quality) => format!("{} {}", quality, object.name),
+            None => object.name.clone(),
+        },
+        _ => full_name(object),
+    }
+}
+
+pub fn is_unidentified(object: &Object) -> bool {
+    object.enchantment.map_or(false, |e| !e.identified)
+}
+
+pub fn identify(object: &mut Object) {
+    if let Some(ref mut enchantment) = object.enchantment {
+        enchantment.identified = true;
+    }
+}
+
+// The bonuses the player knows of: without the magic, until identified.
+pub fn known_equipment(object: &Object) -> Option<Equipment> {
+    let mut equipment = object.equipment?;
+    if let Some(enchantment) = object.enchantment.filter(|e| !e.identified) {
+        adjust(&mut equipment, -enchantment.bonus);
+        for affix in enchantment.prefix.iter().chain(&enchantment.suffix) {
+            apply_affix(*affix, &mut equipment, -1);
+        }
+    }
+    Some(equipment)
+}
+
+// Whether an item is cursed and worn, so it can't be taken off, dropped or
+// parted with. Says so if it is.
+pub fn is_stuck(inventory_id: usize, game: &mut Game) -> bool {
+    let item = &game.inventory[inventory_id];
+    let stuck = item.enchantment.map_or(false, |e| e.cursed) && item.equipment.map_or(false, |e| e.equipped);
+    if stuck {
+        let name = item_name(&game.inventory[inventory_id], game);
+        game.log.add(format!("The {} is cursed! You can't let go of it.", name), colors::RED);
+    }
+    stuck
+}
+
+// A curse makes itself known as soon as the item is put on.
+pub fn reveal_curse(object: &mut Object, log: &mut Messages) {
+    if let Some(ref mut enchantment) = object.enchantment {
+        if enchantment.cursed && !enchantment.identified {
+            enchantment.identified = true;
+            log.add(format!("A chill runs through you. The {} is cursed!", object.name), colors::RED);
+        }
+    }
+}
+
+fn worn_affixes(object: &Object, game: &Game) -> Vec<Affix> {
+    if !object.is_player() {
+        return vec![];
+    }
+    game.inventory.iter()
+        .filter(|item| item.equipment.map_or(false, |e| e.equipped))
+        .filter_map(|item| item.enchantment)
+        .flat_map(|enchantment| enchantment.prefix.into_iter().chain(enchantment.suffix))
+        .collect()
+}
+
+// What the affixes of the attacker's weapon and the target's armor do once a
+// blow has landed for `damage`.
+pub fn after_hit(attacker: &mut Object, target: &mut Object, damage: i32, game: &mut Game) {
+    for affix in worn_affixes(attacker, game) {
+        match affix {
+            Affix::Vampiric => {
+                let amount = cmp::max(1, damage / VAMPIRIC_DIVISOR);
+                attacker.heal(amount, game);
+                game.log.add(format!("{} drains {} hit points.", attacker.name, amount), colors::LIGHT_VIOLET);
+            },
+            Affix::Fire if target.alive => {
+                game.log.add(format!("{} is burned for {} hit points.", target.name, FIRE_DAMAGE), colors::ORANGE);
+                if let (Some(xp), Some(fighter)) = (target.take_damage(FIRE_DAMAGE, &mut game.log), attacker.fighter.as_mut()) {
+                    fighter.xp += xp;
+                }
+            },
+            _ => {},
+        }
+    }
+    for affix in worn_affixes(target, game) {
+        if affix == Affix::Thorns && attacker.alive {
+            game.log.add(format!("{} is pricked by thorns for {} hit points.", attacker.name, THORNS_DAMAGE), colors::LIGHT_GREEN);
+            if let (Some(xp), Some(fighter)) = (attacker.take_damage(THORNS_DAMAGE, &mut game.log), target.fighter.as_mut()) {
+                fighter.xp += xp;
+            }
+        }
+    }
+}
+
+fn choose_equipment(game: &Game, header: &str, ui: &mut Ui) -> Option<usize> {
+    let equipment: Vec<usize> = (0..game.inventory.len()).filter(|&id| game.inventory[id].equipment.is_some()).collect();
+    if equipment.is_empty() {
+        return None;
+    }
+    let options: Vec<String> = equipment.iter().map(|&id| item_name(&game.inventory[id], game)).collect();
+    menu(header, &options, Layout::of(&*ui.renderer).inventory_width, &mut *ui.renderer).map(|choice| equipment[choice])
+}
+
+// The scroll of enchant equipment: +1 to a chosen item, which also comes to be
+// known for what it is.
+pub fn read_enchant(_inventory_id: usize, _objects: &mut [Object], game: &mut Game, ui: &mut Ui) -> UseResult {
+    if !game.inventory.iter().any(|item| item.equipment.is_some()) {
+        game.log.add("You have nothing to enchant.", colors::LIGHT_CYAN);
+        return UseResult::Cancelled;
+    }
+    let id = match choose_equipment(game, "Choose an item to enchant:\n", ui) {
+        Some(id) => id,
+        None => return UseResult::Cancelled,
+    };
+    let item = &mut game.inventory[id];
+    let mut enchantment = item.enchantment.unwrap_or(Enchantment {
+        quality: Quality::Common, bonus: 0, prefix: None, suffix: None, cursed: false, identified: true,
+    });
+    enchantment.bonus += 1;
+    enchantment.identified = true;
+    item.enchantment = Some(enchantment);
+    if let Some(ref mut equipment) = item.equipment {
+        adjust(equipment, 1);
+    }
+    let name = full_name(item);
+    game.log.add(format!("Your {} glows blue for a moment.", name), colors::LIGHT_BLUE);
+    UseResult::UsedUp
+}
+
+// The scroll of remove curse lifts the curse from everything carried.
+pub fn read_remove_curse(_inventory_id: usize, _objects: &mut [Object], game: &mut Game, _ui: &mut Ui) -> UseResult {
+    let mut lifted = 0;
+    for item in game.inventory.iter_mut() {
+        if let Some(ref mut enchantment) = item.enchantment {
+            if enchantment.cursed {
+                enchantment.cursed = false;
+                lifted += 1;
+            }
+        }
+    }
+    if lifted > 0 {
+        game.log.add("You feel as if someone is watching over you.", colors::LIGHT_CYAN);
+    } else {
+        game.log.add("You feel a brief warmth, then nothing.", colors::LIGHT_GREY);
+    }
+    UseResult::UsedUp
+}
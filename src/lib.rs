@@ -0,0 +1,3048 @@
+extern crate tcod;
+extern crate tcod_sys;
+extern crate rand;
+extern crate rustc_serialize;
+#[cfg(unix)]
+extern crate libc;
+
+mod console;
+mod containers;
+mod descriptions;
+mod dice;
+mod durability;
+mod effects;
+mod enchantment;
+mod lighting;
+mod look;
+mod renderer;
+mod pathfinding;
+mod settings;
+pub mod savefile;
+mod simulation;
+mod stealth;
+mod terrain;
+mod targeting;
+mod travel;
+mod view;
+#[cfg(test)]
+mod tests;
+#[cfg(unix)]
+mod ansi;
+
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::ascii::AsciiExt;
+use rand::Rng;
+use rand::distributions::{ Weighted, WeightedChoice, IndependentSample };
+
+use tcod::colors::{ self, Color };
+use tcod::map::FovAlgorithm;
+use tcod::input::{ Key, Event, Mouse };
+
+use console::Console;
+use containers::Container;
+use dice::rng;
+use durability::{Anvil, Durability, Wear};
+use effects::Effect;
+use enchantment::Enchantment;
+use renderer::{ Renderer, TcodRenderer, ScriptedRenderer, wrap_text, print_centered, clear_rect };
+use settings::{ Settings, SETTINGS_PATH };
+use lighting::{ Vision, Light };
+use stealth::Noise;
+use travel::{ Travel, Goal };
+use view::MapView;
+
+type Map = Vec<Vec<Tile>>;
+
+// Where things go on screen, worked out from the console size so that bigger
+// consoles get a bigger map view and more room for messages.
+#[derive(Clone, Copy, Debug)]
+struct Layout {
+    screen_width: i32,
+    screen_height: i32,
+    // The part of the screen above the panel that shows the map.
+    view_width: i32,
+    view_height: i32,
+    panel_y: i32,
+    bar_width: i32,
+    msg_x: i32,
+    msg_width: i32,
+    msg_height: i32,
+    inventory_width: i32,
+}
+
+impl Layout {
+    pub fn new(screen_width: i32, screen_height: i32) -> Layout {
+        let panel_height = cmp::max(7, screen_height / 7);
+        let bar_width = cmp::max(20, screen_width / 4);
+        Layout {
+            screen_width,
+            screen_height,
+            view_width: screen_width,
+            view_height: screen_height - panel_height,
+            panel_y: screen_height - panel_height,
+            bar_width,
+            msg_x: bar_width + 2,
+            msg_width: screen_width - bar_width - 2,
+            msg_height: panel_height - 1,
+            inventory_width: cmp::min(screen_width - 2, cmp::max(50, screen_width * 5 / 8)),
+        }
+    }
+
+    pub fn of(renderer: &dyn Renderer) -> Layout {
+        Layout::new(renderer.width(), renderer.height())
+    }
+}
+
+const PLAYER: usize = 0;
+const ROOM_MAX_SIZE: i32 = 10;
+const ROOM_MIN_SIZE: i32 = 6;
+const MAX_ROOMS: i32 = 40;
+const HEAL_AMOUNT: i32 = 30;
+const LIGHTNING_RANGE: i32 = 8;
+const LIGHTNING_DAMAGE: i32 = 40;
+const FIREBALL_RADIUS: i32 = 3;
+const FIREBALL_DAMAGE: i32 = 25;
+const CONFUSE_RANGE: i32 = 8;
+const CONFUSE_NUM_TURNS: i32 = 8;
+const POISON_CLOUD_RANGE: i32 = 8;
+const POISON_CLOUD_RADIUS: i32 = 2;
+const LEVEL_UP_BASE: i32 = 200;
+const LEVEL_UP_FACTOR: i32 = 150;
+const LEVEL_SCREEN_WIDTH: i32 = 40;
+const CHARACTER_SCREEN_WIDTH: i32 = 56;
+const PERK_LEVEL_INTERVAL: i32 = 3;
+const TEXT_INPUT_MAX_LENGTH: usize = 24;
+const INVENTORY_SIZE: usize = 26;
+const GOLD_CHANCE: u32 = 40;
+const STEAL_CHANCE: u32 = 30;
+const SHOP_MIN_WARES: u32 = 4;
+const SHOP_MAX_WARES: u32 = 8;
+const MANA_REGEN_INTERVAL: u32 = 3;
+
+const POTION_APPEARANCES: &[&str] = &[
+    "murky", "bubbling", "fizzy", "smoky", "cloudy", "glowing", "viscous", "swirling", "golden", "milky",
+];
+const SCROLL_SYLLABLES: &[&str] = &[
+    "xyz", "zy", "ab", "ra", "ka", "dab", "nel", "ith", "mor", "pra", "tu", "gol", "vex", "ul", "bo",
+];
+const UNIDENTIFIED_POTIONS: &[Item] = &[Item::Heal];
+const UNIDENTIFIED_SCROLLS: &[Item] = &[Item::Lightning, Item::Fireball, Item::Confuse, Item::PoisonCloud, Item::Identify, Item::Enchant, Item::RemoveCurse];
+
+const FOV_LIGHT_WALLS: bool = true;
+// How far the player's torch lights.
+const TORCH_RADIUS: i32 = 10;
+
+const COLOR_DARK_WALL: Color = Color { r: 0, g: 0, b: 100 };
+const COLOR_DARK_GROUND: Color = Color { r: 50, g: 50, b: 150 };
+const COLOR_LIGHT_WALL: Color = Color { r: 130, g: 110, b: 50 };
+const COLOR_LIGHT_GROUND: Color = Color { r: 200, g: 180, b: 50 };
+
+type Messages = Vec<(String, Color)>;
+trait MessageLog {
+    fn add<T: Into<String>>(&mut self, message: T, color: Color);
+}
+impl MessageLog for Vec<(String, Color)> {
+    fn add<T: Into<String>>(&mut self, message: T, color: Color) {
+        self.push((message.into(), color));
+    }
+}
+
+#[derive(Clone, Copy, Debug, RustcEncodable, RustcDecodable)]
+struct Tile {
+    blocked: bool,
+    block_sight: bool,
+    explored: bool,
+    // Part of a room lit all over, visible from anywhere in sight.
+    lit: bool,
+    // Dry brush, which fire spreads over.
+    flammable: bool,
+    effect: Option<Effect>,
+    // Open or closed; closed doors block movement and sight.
+    door: bool,
+    // Blows it takes to dig through a wall; see `terrain`.
+    hardness: i32,
+    // Split by age, so that a blast can bring it down.
+    cracked: bool,
+}
+
+impl Tile {
+    pub fn empty() -> Self {
+        Tile { blocked: false, block_sight: false, explored: false, lit: false, flammable: false, effect: None, door: false, hardness: 0, cracked: false }
+    }
+
+    pub fn wall() -> Self {
+        Tile { blocked: true, block_sight: true, explored: false, lit: false, flammable: false, effect: None, door: false, hardness: terrain::ROCK_HARDNESS, cracked: false }
+    }
+
+    // A closed door.
+    pub fn door() -> Self {
+        Tile { door: true, hardness: 1, ..Tile::wall() }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Rect {
+    x1: i32,
+    y1: i32,
+    x2: i32,
+    y2: i32,
+}
+
+impl Rect {
+    pub fn new(x: i32, y: i32, w: i32, h: i32) -> Self {
+        Rect { x1: x, y1: y, x2: x + w, y2: y + h }
+    }
+
+    pub fn center(&self) -> (i32, i32) {
+        let center_x = (self.x1 + self.x2) / 2;
+        let center_y = (self.y1 + self.y2) / 2;
+        (center_x, center_y)
+    }
+
+    pub fn intersects_with(&self, other: &Rect) -> bool {
+        (self.x1 <= other.x2) && (self.x2 >= other.x1) && 
+                (self.y1 <= other.y2) && (self.y2 >= other.y1)
+    }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        (x > self.x1) && (x < self.x2) && (y > self.y1) && (y < self.y2)
+    }
+}
+
+#[derive(Debug, RustcEncodable, RustcDecodable)]
+struct Object {
+    x: i32,
+    y: i32,
+    name: String,
+    level: i32,
+    blocks: bool,
+    always_visible: bool,
+    // An item the player has seen, which stays on the map where they saw it.
+    seen: bool,
+    alive: bool,
+    char: char,
+    color: Color,
+    fighter: Option<Fighter>,
+    ai: Option<Ai>,
+    item: Option<Item>,
+    equipment: Option<Equipment>,
+    gold: i32,
+    inventory: Vec<Object>,
+    caster: Option<Caster>,
+    character: Option<Character>,
+    light: Option<Light>,
+    container: Option<Container>,
+    enchantment: Option<Enchantment>,
+    durability: Option<Durability>,
+    anvil: Option<Anvil>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+struct Fighter {
+    base_max_hp: i32,
+    hp: i32,
+    xp: i32,
+    base_defense: i32,
+    base_power: i32,
+    on_death: DeathCallback,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+enum Item {
+    Heal,
+    Lightning,
+    Fireball,
+    Confuse,
+    PoisonCloud,
+    Identify,
+    Enchant,
+    RemoveCurse,
+    Sword,
+    Shield,
+    Lantern,
+    Pickaxe,
+    Bag,
+    RepairKit,
+    Corpse,
+    Gold,
+    Spellbook(Spell),
+}
+
+impl Item {
+    // Potions and scrolls that hold a single charge of a spell.
+    pub fn spell(self) -> Option<Spell> {
+        match self {
+            Item::Heal => Some(Spell::Heal),
+            Item::Lightning => Some(Spell::Lightning),
+            Item::Fireball => Some(Spell::Fireball),
+            Item::Confuse => Some(Spell::Confuse),
+            Item::PoisonCloud => Some(Spell::PoisonCloud),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+enum Spell {
+    Heal,
+    Lightning,
+    Fireball,
+    Confuse,
+    PoisonCloud,
+    Dig,
+}
+
+const SPELLS: &[Spell] = &[Spell::Heal, Spell::Lightning, Spell::Fireball, Spell::Confuse, Spell::PoisonCloud, Spell::Dig];
+
+impl std::fmt::Display for Spell {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Spell::Heal => write!(f, "healing"),
+            Spell::Lightning => write!(f, "lightning bolt"),
+            Spell::Fireball => write!(f, "fireball"),
+            Spell::Confuse => write!(f, "confusion"),
+            Spell::PoisonCloud => write!(f, "poison cloud"),
+            Spell::Dig => write!(f, "digging"),
+        }
+    }
+}
+
+impl Spell {
+    pub fn cost(self) -> i32 {
+        match self {
+            Spell::Heal => 10,
+            Spell::Lightning => 12,
+            Spell::Fireball => 20,
+            Spell::Confuse => 8,
+            Spell::PoisonCloud => 15,
+            Spell::Dig => 10,
+        }
+    }
+
+    // Turns that must pass before the spell can be cast again.
+    pub fn cooldown(self) -> i32 {
+        match self {
+            Spell::Heal => 10,
+            Spell::Lightning => 5,
+            Spell::Fireball => 10,
+            Spell::Confuse => 5,
+            Spell::PoisonCloud => 10,
+            Spell::Dig => 5,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+struct KnownSpell {
+    spell: Spell,
+    cooldown: i32,
+}
+
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+struct Caster {
+    max_mana: i32,
+    mana: i32,
+    regen_turns: u32,
+    spells: Vec<KnownSpell>,
+}
+
+impl Caster {
+    pub fn new(max_mana: i32) -> Self {
+        Caster { max_mana, mana: max_mana, regen_turns: 0, spells: vec![] }
+    }
+
+    pub fn knows(&self, spell: Spell) -> bool {
+        self.spells.iter().any(|known| known.spell == spell)
+    }
+
+    pub fn learn(&mut self, spell: Spell) {
+        if !self.knows(spell) {
+            self.spells.push(KnownSpell { spell, cooldown: 0 });
+        }
+    }
+
+    pub fn recover(&mut self, max_mana: i32, regen_interval: u32) {
+        for known in self.spells.iter_mut() {
+            if known.cooldown > 0 {
+                known.cooldown -= 1;
+            }
+        }
+        self.regen_turns += 1;
+        if self.regen_turns >= regen_interval {
+            self.regen_turns = 0;
+            if self.mana < max_mana {
+                self.mana += 1;
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum UseResult {
+    UsedUp,
+    UsedAndKept,
+    Cancelled,
+}
+
+// What the player knows about a kind of potion or scroll. Until identified, items of
+// that kind are shown by their randomized appearance (or the name the player called them).
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+struct ItemKnowledge {
+    item: Item,
+    appearance: String,
+    identified: bool,
+    called: Option<String>,
+}
+
+fn new_item_knowledge() -> Vec<ItemKnowledge> {
+    let mut rng = rng();
+    let mut appearances = POTION_APPEARANCES.to_vec();
+    rng.shuffle(&mut appearances);
+
+    let mut knowledge: Vec<ItemKnowledge> = UNIDENTIFIED_POTIONS.iter().zip(appearances).map(|(&item, appearance)| {
+        ItemKnowledge { item, appearance: format!("{} potion", appearance), identified: false, called: None }
+    }).collect();
+
+    for &item in UNIDENTIFIED_SCROLLS {
+        let appearance = loop {
+            let syllables = rng.gen_range(2, 4);
+            let label = (0..syllables).map(|_| *rng.choose(SCROLL_SYLLABLES).unwrap()).collect::<String>();
+            let appearance = format!("scroll labeled {}", label.to_uppercase());
+            if !knowledge.iter().any(|k| k.appearance == appearance) {
+                break appearance;
+            }
+        };
+        knowledge.push(ItemKnowledge { item, appearance, identified: false, called: None });
+    }
+    knowledge
+}
+
+fn item_knowledge(item: Item, game: &Game) -> Option<&ItemKnowledge> {
+    game.item_knowledge.iter().find(|k| k.item == item)
+}
+
+fn is_unidentified(object: &Object, game: &Game) -> bool {
+    match object.item.and_then(|item| item_knowledge(item, game)) {
+        Some(knowledge) => !knowledge.identified,
+        None => enchantment::is_unidentified(object),
+    }
+}
+
+// The name the player sees for an object: its true name, unless it is an
+// unidentified potion or scroll, or equipment whose magic isn't known yet.
+fn item_name(object: &Object, game: &Game) -> String {
+    match object.item.and_then(|item| item_knowledge(item, game)) {
+        Some(knowledge) if !knowledge.identified => {
+            match knowledge.called {
+                Some(ref called) => format!("{} called {}", knowledge.appearance, called),
+                None => knowledge.appearance.clone(),
+            }
+        },
+        _ => enchantment::known_name(object),
+    }
+}
+
+fn identify_item(item: Item, name: &str, game: &mut Game) {
+    let appearance = match game.item_knowledge.iter_mut().find(|k| k.item == item && !k.identified) {
+        Some(knowledge) => {
+            knowledge.identified = true;
+            knowledge.appearance.clone()
+        },
+        None => return,
+    };
+    game.log.add(format!("The {} was a {}!", appearance, name), colors::LIGHT_CYAN);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Transition {
+    level: u32,
+    value: u32,
+}
+
+fn from_dungeon_level(table: &[Transition], level: u32) -> u32 {
+    table.iter().rev().find(|transition| level >= transition.level).map_or(0, |transision| transision.value)
+}
+
+fn next_level(ui: &mut Ui, objects: &mut Vec<Object>, game: &mut Game) {
+    game.log.add("You take a moment to rest and recover your strength.", colors::VIOLET);
+    let heal_hp = objects[PLAYER].max_hp(game) / 2;
+    objects[PLAYER].heal(heal_hp, game);
+
+    game.log.add("After a rare moment of peace, you descend deeper into the heart of the dungeon...", colors::RED);
+
+    objects.truncate(1);
+    game.dungeon_level += 1;
+    game.map = make_map(game.dungeon_level, objects);
+    initialize_fov(&game.map, ui);
+    update_fov(ui, objects, game);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcDecodable, RustcEncodable)]
+struct Equipment {
+    slot: Slot,
+    equipped: bool,
+    power_bonus: i32,
+    defense_bonus: i32,
+    max_hp_bonus: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcDecodable, RustcEncodable)]
+enum Slot {
+    LeftHand,
+    RightHand,
+    Head,
+    Belt,
+}
+
+const SLOTS: &[Slot] = &[Slot::LeftHand, Slot::RightHand, Slot::Head, Slot::Belt];
+
+impl std::fmt::Display for Slot {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Slot::LeftHand => write!(f, "left hand"),
+            Slot::RightHand => write!(f, "right hand"),
+            Slot::Head => write!(f, "head"),
+            Slot::Belt => write!(f, "belt"),
+        }
+    }
+}
+
+fn drop_item(inventory_id: usize, game: &mut Game, objects: &mut Vec<Object>) {
+    if enchantment::is_stuck(inventory_id, game) {
+        return;
+    }
+    let mut item = game.inventory.remove(inventory_id);
+
+    if item.equipment.is_some() {
+        item.dequip(&mut game.log);
+    }
+
+    item.set_pos(objects[PLAYER].x, objects[PLAYER].y);
+    game.log.add(format!("You dropped a {}", item_name(&item, game)), colors::YELLOW);
+    objects.push(item);
+}
+
+fn pick_item_up(object_id: usize, game: &mut Game, objects: &mut Vec<Object>) {
+    if objects[object_id].item == Some(Item::Gold) {
+        let pile = objects.swap_remove(object_id);
+        game.gold += pile.gold;
+        game.log.add(format!("You picked up {} gold pieces.", pile.gold), colors::GOLD);
+    } else if game.inventory.len() >= INVENTORY_SIZE {
+        game.log.add(format!("Your inventory is full, cannot pick up {}.", item_name(&objects[object_id], game)), colors::RED);
+    } else {
+        let item = objects.swap_remove(object_id);
+        game.log.add(format!("You picked up a {}!", item_name(&item, game)), colors::GREEN);
+
+        let index = game.inventory.len();
+        let slot = item.equipment.map(|e| e.slot);
+        game.inventory.push(item);
+
+        if let Some(slot) = slot {
+            if get_equipped_in_slot(slot, &game.inventory).is_none() {
+                game.inventory[index].equip(&mut game.log);
+            }
+        }
+    }
+}
+
+// The items under the player, in the order the pickup menu lists them.
+fn items_here(objects: &[Object]) -> Vec<usize> {
+    let here = objects[PLAYER].pos();
+    (0..objects.len()).filter(|&id| objects[id].pos() == here && objects[id].item.is_some()).collect()
+}
+
+// Picks up the one item under the player, or any or all of a pile, chosen from
+// a menu. Only takes a turn if something was picked up.
+fn pick_up(game: &mut Game, objects: &mut Vec<Object>, ui: &mut Ui) -> PlayerAction {
+    let here = items_here(objects);
+    let chosen = match here.len() {
+        0 => {
+            game.log.add("There is nothing here to pick up.", colors::LIGHT_GREY);
+            return PlayerAction::DidntTakeTurn;
+        },
+        1 => here,
+        _ => {
+            // The menu has room for 26 options, one of them for the whole pile.
+            let mut options: Vec<String> = here.iter().take(25).map(|&id| item_name(&objects[id], game)).collect();
+            options.push("(pick up all)".to_string());
+            let width = Layout::of(&*ui.renderer).inventory_width;
+            match menu("Press the key next to an item to pick it up, or any other to cancel.\n", &options, width, &mut *ui.renderer) {
+                Some(index) if index < options.len() - 1 => vec![here[index]],
+                Some(_) => here,
+                None => return PlayerAction::DidntTakeTurn,
+            }
+        },
+    };
+
+    // Highest first, since picking an item up moves the last object into its
+    // place. Whatever is picked up leaves the map, so fewer objects afterwards
+    // means something was.
+    let before = objects.len();
+    let mut left_behind = false;
+    for &id in chosen.iter().rev() {
+        if chosen.len() > 1 && game.inventory.len() >= INVENTORY_SIZE && objects[id].item != Some(Item::Gold) {
+            left_behind = true;
+        } else {
+            pick_item_up(id, game, objects);
+        }
+    }
+    if left_behind {
+        game.log.add("Your inventory is full, the rest stays where it is.", colors::RED);
+    }
+    if objects.len() < before {
+        PlayerAction::TookTurn
+    } else {
+        PlayerAction::DidntTakeTurn
+    }
+}
+
+fn use_item(inventory_id: usize, game: &mut Game, objects: &mut [Object], ui: &mut Ui) {
+    if let Some(item) = game.inventory[inventory_id].item {
+        let on_use = match item {
+            Item::Heal => release_spell,
+            Item::Lightning => release_spell,
+            Item::Fireball => release_spell,
+            Item::Confuse => release_spell,
+            Item::PoisonCloud => release_spell,
+            Item::Identify => cast_identify,
+            Item::Enchant => enchantment::read_enchant,
+            Item::RemoveCurse => enchantment::read_remove_curse,
+            Item::Spellbook(_) => read_spellbook,
+            Item::Corpse => eat_corpse,
+            Item::Sword => toggle_equipment,
+            Item::Shield => toggle_equipment,
+            Item::Lantern => toggle_equipment,
+            Item::Pickaxe => toggle_equipment,
+            Item::Bag => containers::open_bag,
+            Item::RepairKit => durability::use_repair_kit,
+            Item::Gold => unreachable!(),
+        };
+        let name = game.inventory[inventory_id].name.clone();
+        match on_use(inventory_id, objects, game, ui) {
+            UseResult::UsedUp => {
+                game.inventory.remove(inventory_id);
+                identify_item(item, &name, game);
+            },
+            UseResult::UsedAndKept => {},
+            UseResult::Cancelled => {
+                game.log.add("Cancelled", colors::WHITE);
+            }
+        }
+    } else {
+        game.log.add(format!("The {} cannot be used.", item_name(&game.inventory[inventory_id], game)), colors::WHITE);
+    }
+}
+
+fn get_equipped_in_slot(slot: Slot, inventory: &[Object]) -> Option<usize> {
+    for (inventory_id, item) in inventory.iter().enumerate() {
+        if item.equipment.as_ref().map_or(false, |e| e.equipped && e.slot == slot) {
+            return Some(inventory_id)
+        }
+    }
+    None
+}
+
+fn toggle_equipment(inventory_id: usize, _objects: &mut [Object], game: &mut Game, _ui: &mut Ui) -> UseResult {
+    let equipment = match game.inventory[inventory_id].equipment {
+        Some(equipment) => equipment,
+        None => return UseResult::Cancelled,
+    };
+
+    if equipment.equipped {
+        if !enchantment::is_stuck(inventory_id, game) {
+            game.inventory[inventory_id].dequip(&mut game.log);
+        }
+    } else {
+
+        if let Some(old_equipment) = get_equipped_in_slot(equipment.slot, &game.inventory) {
+            if enchantment::is_stuck(old_equipment, game) {
+                return UseResult::UsedAndKept;
+            }
+            game.inventory[old_equipment].dequip(&mut game.log);
+        }
+        game.inventory[inventory_id].equip(&mut game.log);
+    }
+    UseResult::UsedAndKept
+}
+
+fn closest_monster(max_range: i32, objects: &mut [Object], ui: &Ui) -> Option<usize> {
+    let mut closest_enemy = None;
+    let mut closest_dist = (max_range + 1) as f32;
+    for (id, object) in objects.iter().enumerate() {
+        if (id != PLAYER) && object.fighter.is_some() && object.ai.is_some() && ui.fov.is_in_fov(object.x, object.y) {
+            let dist = objects[PLAYER].distance_to(object);
+            if dist < closest_dist {
+                closest_enemy = Some(id);
+                closest_dist = dist;
+            }
+        }
+    }
+    closest_enemy
+}
+
+fn cast_spell(spell: Spell, objects: &mut [Object], game: &mut Game, ui: &mut Ui) -> UseResult {
+    let effect = match spell {
+        Spell::Heal => cast_heal,
+        Spell::Lightning => cast_lightning,
+        Spell::Fireball => cast_fireball,
+        Spell::Confuse => cast_confuse,
+        Spell::PoisonCloud => cast_poison_cloud,
+        Spell::Dig => cast_dig,
+    };
+    effect(objects, game, ui)
+}
+
+fn release_spell(inventory_id: usize, objects: &mut [Object], game: &mut Game, ui: &mut Ui) -> UseResult {
+    match game.inventory[inventory_id].item.and_then(|item| item.spell()) {
+        Some(spell) => cast_spell(spell, objects, game, ui),
+        None => UseResult::Cancelled,
+    }
+}
+
+fn read_spellbook(inventory_id: usize, objects: &mut [Object], game: &mut Game, _ui: &mut Ui) -> UseResult {
+    let spell = match game.inventory[inventory_id].item {
+        Some(Item::Spellbook(spell)) => spell,
+        _ => return UseResult::Cancelled,
+    };
+    match objects[PLAYER].caster {
+        Some(ref caster) if caster.knows(spell) => {
+            game.log.add(format!("You already know the {} spell.", spell), colors::WHITE);
+            UseResult::Cancelled
+        },
+        Some(ref mut caster) => {
+            caster.learn(spell);
+            game.log.add(format!("You study the spellbook and learn the {} spell!", spell), colors::LIGHT_PURPLE);
+            UseResult::UsedUp
+        },
+        None => {
+            game.log.add("The runes in the spellbook mean nothing to you.", colors::WHITE);
+            UseResult::Cancelled
+        },
+    }
+}
+
+fn cast_known_spell(objects: &mut [Object], game: &mut Game, ui: &mut Ui) -> PlayerAction {
+    let caster = match objects[PLAYER].caster {
+        Some(ref caster) if !caster.spells.is_empty() => caster.clone(),
+        _ => {
+            game.log.add("You don't know any spells.", colors::WHITE);
+            return PlayerAction::DidntTakeTurn;
+        },
+    };
+
+    let options: Vec<String> = caster.spells.iter().map(|known| {
+        let cost = objects[PLAYER].spell_cost(known.spell);
+        if known.cooldown > 0 {
+            format!("{} ({} mana, ready in {} turns)", known.spell, cost, known.cooldown)
+        } else {
+            format!("{} ({} mana)", known.spell, cost)
+        }
+    }).collect();
+    let header = format!("Mana: {}/{}. Press the key next to a spell to cast it, or any other to cancel.\n",
+                         caster.mana, objects[PLAYER].max_mana());
+    let choice = match menu(&header, &options, Layout::of(&*ui.renderer).inventory_width, &mut *ui.renderer) {
+        Some(choice) => choice,
+        None => return PlayerAction::DidntTakeTurn,
+    };
+
+    let known = caster.spells[choice];
+    let cost = objects[PLAYER].spell_cost(known.spell);
+    if known.cooldown > 0 {
+        game.log.add(format!("You cannot cast {} again for {} turns.", known.spell, known.cooldown), colors::RED);
+        return PlayerAction::DidntTakeTurn;
+    }
+    if caster.mana < cost {
+        game.log.add(format!("You don't have enough mana to cast {}.", known.spell), colors::RED);
+        return PlayerAction::DidntTakeTurn;
+    }
+
+    match cast_spell(known.spell, objects, game, ui) {
+        UseResult::Cancelled => {
+            game.log.add("Cancelled", colors::WHITE);
+            PlayerAction::DidntTakeTurn
+        },
+        _ => {
+            let caster = objects[PLAYER].caster.as_mut().unwrap();
+            caster.mana -= cost;
+            caster.spells[choice].cooldown = known.spell.cooldown();
+            PlayerAction::TookTurn
+        },
+    }
+}
+
+fn learn_random_spell(player: &mut Object, log: &mut Messages) {
+    if let Some(ref mut caster) = player.caster {
+        let unknown: Vec<Spell> = SPELLS.iter().cloned().filter(|&spell| !caster.knows(spell)).collect();
+        match rng().choose(&unknown) {
+            Some(&spell) => {
+                caster.learn(spell);
+                log.add(format!("You have learned the {} spell!", spell), colors::LIGHT_PURPLE);
+            },
+            None => log.add("You already know every spell there is to know.", colors::LIGHT_PURPLE),
+        }
+    }
+}
+
+fn cast_fireball(objects: &mut [Object], game: &mut Game, ui: &mut Ui) -> UseResult {
+    game.log.add("Choose a target tile for the fireball with the mouse or the movement keys, or Escape to cancel.", colors::LIGHT_CYAN);
+    let (x, y) = match targeting::target_tile(ui, objects, game, None, Some(FIREBALL_RADIUS)) {
+        Some(tile_pos) => tile_pos,
+        None => return UseResult::Cancelled,
+    };
+    game.log.add(format!("The fireball explodes, burning everything within {} tiles!", FIREBALL_RADIUS), colors::ORANGE);
+    stealth::make_noise(ui, stealth::EXPLOSION_NOISE);
+    let mut xp_to_gain = 0;
+    for (id, obj) in objects.iter_mut().enumerate() {
+        if id == PLAYER && game.god_mode {
+            continue;
+        }
+        // Walls shelter whatever is behind them from the blast.
+        let in_blast = obj.distance(x, y) <= FIREBALL_RADIUS as f32 && effects::line_of_effect(&game.map, (x, y), obj.pos());
+        if in_blast && obj.fighter.is_some() {
+            game.log.add(format!("The {} gets burned for {} hit points.", obj.name, FIREBALL_DAMAGE), colors::ORANGE);
+            if let Some(xp) = obj.take_damage(FIREBALL_DAMAGE, &mut game.log) {
+                if id != PLAYER {
+                    xp_to_gain += xp
+                }
+            }
+        }
+    }
+    objects[PLAYER].fighter.as_mut().unwrap().xp += xp_to_gain;
+    if terrain::blast(game, (x, y), FIREBALL_RADIUS) > 0 {
+        game.log.add("The blast brings down part of the walls around it.", colors::ORANGE);
+    }
+    effects::ignite_area(&mut game.map, (x, y), FIREBALL_RADIUS);
+
+    UseResult::UsedUp
+}
+
+fn cast_lightning(objects: &mut [Object], game: &mut Game, ui: &mut Ui) -> UseResult {
+    let monster_id = closest_monster(LIGHTNING_RANGE, objects, ui);
+    if let Some(monster_id) = monster_id {
+        stealth::make_noise(ui, stealth::THUNDER_NOISE);
+        game.log.add(
+                format!("A lightning bold strikes the {} with a loud thunder! The damage is {} hit points.", objects[monster_id].name, LIGHTNING_DAMAGE), 
+                colors::LIGHT_BLUE);
+        if let Some(xp) = objects[monster_id].take_damage(LIGHTNING_DAMAGE, &mut game.log) {
+            objects[PLAYER].fighter.as_mut().unwrap().xp += xp;
+        }
+        UseResult::UsedUp
+    } else {
+        game.log.add("No enemy is close enough to strike.", colors::RED);
+        UseResult::Cancelled
+    }
+}
+
+fn cast_confuse(objects: &mut [Object], game: &mut Game, ui: &mut Ui) -> UseResult {
+    game.log.add("Choose an enemy to confuse, Tab to cycle through them, or Escape to cancel.", colors::LIGHT_CYAN);
+    let monster_id = targeting::target_monster(ui, objects, game, Some(CONFUSE_RANGE as f32));
+    if let Some(monster_id) = monster_id {
+        let old_ai = objects[monster_id].ai.take().unwrap_or(Ai::Basic);
+        objects[monster_id].ai = Some(Ai::Confused {
+            previous_ai: Box::new(old_ai),
+            num_turns: CONFUSE_NUM_TURNS,
+        });
+        game.log.add(format!("The eyes of {} look vacent and he starts to stumble around!", objects[monster_id].name), colors::LIGHT_GREEN);
+        UseResult::UsedUp
+    } else {
+        game.log.add("No enemy is close enough to strike.", colors::RED);
+        UseResult::Cancelled
+    }
+}
+
+fn cast_poison_cloud(objects: &mut [Object], game: &mut Game, ui: &mut Ui) -> UseResult {
+    game.log.add("Choose where to release the poison cloud with the mouse or the movement keys, or Escape to cancel.", colors::LIGHT_CYAN);
+    let (x, y) = match targeting::target_tile(ui, objects, game, Some(POISON_CLOUD_RANGE as f32), Some(POISON_CLOUD_RADIUS)) {
+        Some(tile_pos) => tile_pos,
+        None => return UseResult::Cancelled,
+    };
+    game.log.add("A cloud of choking green gas billows out.", colors::CHARTREUSE);
+    effects::release_gas(&mut game.map, (x, y), POISON_CLOUD_RADIUS);
+    UseResult::UsedUp
+}
+
+fn cast_dig(objects: &mut [Object], game: &mut Game, ui: &mut Ui) -> UseResult {
+    game.log.add("Choose which way to dig with the mouse or the movement keys, or Escape to cancel.", colors::LIGHT_CYAN);
+    let target = match targeting::target_tile(ui, objects, game, Some(terrain::DIG_RANGE as f32), None) {
+        Some(tile_pos) => tile_pos,
+        None => return UseResult::Cancelled,
+    };
+    if terrain::tunnel(game, objects[PLAYER].pos(), target) == 0 {
+        game.log.add("Nothing there yields to the spell.", colors::LIGHT_GREY);
+        return UseResult::Cancelled;
+    }
+    game.log.add("The rock crumbles away into dust.", colors::LIGHT_SEPIA);
+    UseResult::UsedUp
+}
+
+fn cast_heal(objects: &mut [Object], game: &mut Game, _ui: &mut Ui) -> UseResult {
+    let player = &mut objects[PLAYER];
+    if let Some(fighter) = player.fighter {
+        if fighter.hp == player.max_hp(game) {
+            game.log.add("You are already at full health.", colors::RED);
+            return UseResult::Cancelled;
+        }
+        game.log.add("Your wounds start to feel better!", colors::LIGHT_VIOLET);
+        player.heal(HEAL_AMOUNT, game);
+        return UseResult::UsedUp;
+    }
+    UseResult::Cancelled
+}
+
+fn cast_identify(inventory_id: usize, _objects: &mut [Object], game: &mut Game, ui: &mut Ui) -> UseResult {
+    let unidentified: Vec<usize> = (0..game.inventory.len()).filter(|&id| {
+        id != inventory_id && is_unidentified(&game.inventory[id], game)
+    }).collect();
+
+    if unidentified.is_empty() {
+        game.log.add("You have nothing left to identify.", colors::LIGHT_CYAN);
+        return UseResult::Cancelled;
+    }
+
+    let options: Vec<String> = unidentified.iter().map(|&id| item_name(&game.inventory[id], game)).collect();
+    match menu("Choose an item to identify:\n", &options, Layout::of(&*ui.renderer).inventory_width, &mut *ui.renderer) {
+        Some(choice) if enchantment::is_unidentified(&game.inventory[unidentified[choice]]) => {
+            let object = &mut game.inventory[unidentified[choice]];
+            enchantment::identify(object);
+            let name = enchantment::full_name(object);
+            game.log.add(format!("It is a {}!", name), colors::LIGHT_CYAN);
+            UseResult::UsedUp
+        },
+        Some(choice) => {
+            let object = &game.inventory[unidentified[choice]];
+            let (item, name) = (object.item.unwrap(), object.name.clone());
+            identify_item(item, &name, game);
+            UseResult::UsedUp
+        },
+        None => UseResult::Cancelled,
+    }
+}
+
+fn call_item(inventory_id: usize, game: &mut Game, renderer: &mut dyn Renderer) {
+    let item = game.inventory[inventory_id].item;
+    let appearance = match item.and_then(|item| item_knowledge(item, game)) {
+        Some(knowledge) if !knowledge.identified => knowledge.appearance.clone(),
+        Some(_) => {
+            game.log.add(format!("You already know that is a {}.", game.inventory[inventory_id].name), colors::WHITE);
+            return;
+        },
+        None => {
+            game.log.add(format!("There is no need to name the {}.", game.inventory[inventory_id].name), colors::WHITE);
+            return;
+        },
+    };
+
+    if let Some(called) = text_input(&format!("What do you want to call the {}?", appearance), Layout::of(renderer).inventory_width, renderer) {
+        let called = called.trim().to_string();
+        let knowledge = game.item_knowledge.iter_mut().find(|k| Some(k.item) == item).unwrap();
+        knowledge.called = if called.is_empty() { None } else { Some(called) };
+    }
+}
+
+fn eat_corpse(_inventory_id: usize, objects: &mut [Object], game: &mut Game, _ui: &mut Ui) -> UseResult {
+    if objects[PLAYER].fighter.is_some() {
+        game.log.add("You start to dine then think better of it.", colors::GREEN);
+        return UseResult::Cancelled;
+    }
+    UseResult::Cancelled
+}
+
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+enum Ai {
+    Basic,
+    // Hasn't noticed the player yet, and open to a sneak attack.
+    Unaware,
+    Confused { previous_ai: Box<Ai>, num_turns: i32 },
+    Shopkeeper,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+enum DeathCallback {
+    Player,
+    Monster,
+}
+
+impl DeathCallback {
+    fn callback(self, object: &mut Object, messages: &mut Messages) {
+        let callback: fn (&mut Object, &mut Messages) = match self {
+            DeathCallback::Player => player_death,
+            DeathCallback::Monster => monster_death,
+        };
+        callback(object, messages);
+    }
+}
+
+impl Object {
+    pub fn new(x: i32, y: i32, char: char, name: &str, color: Color, blocks: bool) -> Self {
+        Object {
+            x: x,
+            y: y,
+            level: 1,
+            char: char,
+            color: color,
+            name: name.into(),
+            blocks: blocks,
+            always_visible: false,
+            seen: false,
+            alive: false,
+            fighter: None,
+            ai: None,
+            item: None,
+            equipment: None,
+            gold: 0,
+            inventory: vec![],
+            caster: None,
+            character: None,
+            light: None,
+            container: None,
+            enchantment: None,
+            durability: None,
+            anvil: None,
+        }
+    }
+
+    pub fn is_player(&self) -> bool {
+        self.fighter.map(|f| f.on_death) == Some(DeathCallback::Player)
+    }
+
+    pub fn get_all_equipped(&self, game: &Game) -> Vec<Equipment> {
+        if self.is_player() {
+            game.inventory
+                .iter()
+                .filter(|item| { 
+                    item.equipment.map_or(false, |e| e.equipped)
+                })
+                .filter_map(durability::effective)
+                .collect()
+        } else {
+            vec![]
+        }
+    }
+
+    pub fn power(&self, game: &Game) -> i32 {
+        let base_power = self.fighter.map_or(0, |f| f.base_power);
+        let bonus = self.get_all_equipped(game).iter().fold(0, |sum, e| sum + e.power_bonus);
+        let character_bonus = self.character.as_ref().map_or(0, |c| c.power_bonus());
+        base_power + bonus + character_bonus
+    }
+
+    pub fn defense(&self, game: &Game) -> i32 {
+        let base_defense = self.fighter.map_or(0, |f| f.base_defense);
+        let bonus = self.get_all_equipped(game).iter().fold(0, |sum, e| sum + e.defense_bonus);
+        let character_bonus = self.character.as_ref().map_or(0, |c| c.defense_bonus());
+        base_defense + bonus + character_bonus
+    }
+    
+    pub fn max_hp(&self, game: &Game) -> i32 {
+        let base_max_hp = self.fighter.map_or(0, |f| f.base_max_hp);
+        let bonus = self.get_all_equipped(game).iter().fold(0, |sum, e| sum + e.max_hp_bonus);
+        let character_bonus = self.character.as_ref().map_or(0, |c| c.max_hp_bonus());
+        base_max_hp + bonus + character_bonus
+    }
+
+    pub fn max_mana(&self) -> i32 {
+        let base_max_mana = self.caster.as_ref().map_or(0, |c| c.max_mana);
+        let character_bonus = self.character.as_ref().map_or(0, |c| c.max_mana_bonus());
+        cmp::max(0, base_max_mana + character_bonus)
+    }
+
+    pub fn spell_cost(&self, spell: Spell) -> i32 {
+        let magic = self.character.as_ref().map_or(0, |c| c.magic);
+        cmp::max(1, spell.cost() - magic)
+    }
+
+    pub fn restore_mana(&mut self, amount: i32) {
+        let max_mana = self.max_mana();
+        if let Some(ref mut caster) = self.caster {
+            caster.mana = cmp::min(caster.mana + amount, max_mana);
+        }
+    }
+
+    // Called once per player turn: spells cool down and mana slowly returns.
+    pub fn recover_mana(&mut self) {
+        let max_mana = self.max_mana();
+        let interval = self.character.as_ref().map_or(MANA_REGEN_INTERVAL, |c| c.mana_regen_interval());
+        if let Some(ref mut caster) = self.caster {
+            caster.recover(max_mana, interval);
+        }
+    }
+
+    pub fn equip(&mut self, log: &mut Vec<(String, Color)>) {
+        if self.item.is_none() {
+            log.add(format!("Can't equip {:?} because it is not an Item.", self), colors::RED);
+            return
+        };
+
+        if let Some(ref mut equipment) = self.equipment {
+            if !equipment.equipped {
+                equipment.equipped = true;
+                log.add(format!("Equipped {} on {}.", self.name, equipment.slot), colors::LIGHT_GREEN);
+            }
+            enchantment::reveal_curse(self, log);
+        } else {
+            log.add(format!("Can't equip {:?} because it is not an Equipment.", self), colors::RED);
+        }
+    }
+
+    pub fn dequip(&mut self, log: &mut Vec<(String, Color)>) {
+        if self.item.is_none() {
+            log.add(format!("Can't dequip {:?} because it is not an Item.", self), colors::RED);
+            return
+        };
+
+        if let Some(ref mut equipment) = self.equipment {
+            if equipment.equipped {
+                equipment.equipped = false;
+                log.add(format!("Dequipped {} from {}.", self.name, equipment.slot), colors::LIGHT_YELLOW);
+            }
+        } else {
+            log.add(format!("Can't dequip {:?} because it is not an Equipment.", self), colors::RED);
+        }
+    }
+
+    pub fn pos(&self) -> (i32, i32) {
+        (self.x, self.y)
+    }
+
+    // Wherever it goes, the player only knows it is there once they see it.
+    pub fn set_pos(&mut self, x: i32, y: i32) {
+        self.x = x;
+        self.y = y;
+        self.seen = false;
+    }
+
+    pub fn distance_to(&self, other: &Object) -> f32 {
+        self.distance(other.x, other.y)
+    }
+
+    pub fn distance(&self, x: i32, y: i32) -> f32 {
+        (((x - self.x).pow(2) + (y - self.y).pow(2)) as f32).sqrt()
+    }
+
+    pub fn take_damage(&mut self, damage: i32, messages: &mut Messages) -> Option<i32> {
+        if let Some(fighter) = self.fighter.as_mut() {
+            if damage > 0 {
+                fighter.hp -= damage;
+            }
+        }
+        // Nothing wakes a monster up like getting hurt.
+        if self.ai == Some(Ai::Unaware) {
+            self.ai = Some(Ai::Basic);
+        }
+
+        if let Some(fighter) = self.fighter {
+            if fighter.hp <= 0 {
+                self.alive = false;
+                fighter.on_death.callback(self, messages);
+                return Some(fighter.xp);
+            }
+        }
+        None
+    }
+
+    pub fn heal(&mut self, amount: i32, game: &Game) {
+        let max_hp = self.max_hp(game);
+        if let Some(ref mut fighter) = self.fighter {
+            fighter.hp += amount;
+            if fighter.hp > max_hp {
+                fighter.hp = max_hp;
+            }
+        }
+    }
+
+    pub fn attack(&mut self, game: &mut Game, target: &mut Object) {
+        if target.is_player() && game.god_mode {
+            game.log.add(format!("{} attacks {} but the gods turn the blow aside.", self.name, target.name), colors::GREY);
+            return;
+        }
+        let mut power = self.power(game);
+        if target.ai == Some(Ai::Unaware) {
+            game.log.add(format!("{} catches {} unaware!", self.name, target.name), colors::LIGHT_GREEN);
+            power *= stealth::SNEAK_ATTACK_MULTIPLIER;
+            // Hit or miss, it knows about the attacker now.
+            target.ai = Some(Ai::Basic);
+        }
+        let damage = power - target.defense(game);
+        if damage > 0 {
+            game.log.add(format!("{} attacks {} for {} hit points.", self.name, target.name, damage), colors::WHITE);
+            if let (Some(xp), Some(fighter)) = (target.take_damage(damage, &mut game.log), self.fighter.as_mut()) {
+                fighter.xp += xp;
+            }
+            enchantment::after_hit(self, target, damage, game);
+            if self.is_player() {
+                durability::wear(self, Wear::Weapons, game);
+            }
+            if target.is_player() {
+                durability::wear(target, Wear::Armor, game);
+            }
+        } else {
+            game.log.add(format!("{} attacks {} but it has no effect!", self.name, target.name), colors::GREY);
+        }
+    }
+}
+
+// What an item is worth to a shopkeeper. Shops sell at full value and buy
+// at half of it.
+fn item_value(object: &Object) -> i32 {
+    let base = match object.item {
+        Some(Item::Heal) => 20,
+        Some(Item::Lightning) => 60,
+        Some(Item::Fireball) => 80,
+        Some(Item::Confuse) => 40,
+        Some(Item::PoisonCloud) => 50,
+        Some(Item::Identify) => 30,
+        Some(Item::Enchant) => 60,
+        Some(Item::RemoveCurse) => 40,
+        Some(Item::Sword) | Some(Item::Shield) => 10,
+        Some(Item::Lantern) => 30,
+        Some(Item::Pickaxe) => 20,
+        Some(Item::Bag) => 15,
+        Some(Item::RepairKit) => 40,
+        Some(Item::Spellbook(_)) => 100,
+        Some(Item::Corpse) | Some(Item::Gold) | None => 0,
+    };
+    let bonus = durability::effective(object).map_or(0, |e| e.power_bonus * 15 + e.defense_bonus * 20 + e.max_hp_bonus * 2);
+    base + bonus + containers::contents_value(object)
+}
+
+fn sell_price(object: &Object) -> i32 {
+    item_value(object) / 2
+}
+
+fn visit_shop(shopkeeper_id: usize, objects: &mut [Object], game: &mut Game, ui: &mut Ui) {
+    loop {
+        let header = format!("\"Welcome, welcome! Have a look around.\"\nYou have {} gold.\n", game.gold);
+        match menu(&header, &["Buy", "Sell", "Steal", "Leave"], Layout::of(&*ui.renderer).inventory_width, &mut *ui.renderer) {
+            Some(0) => buy_item(shopkeeper_id, objects, game, ui),
+            Some(1) => sell_item(shopkeeper_id, objects, game, ui),
+            Some(2) => {
+                steal_item(shopkeeper_id, objects, game, ui);
+                return;
+            },
+            _ => return,
+        }
+    }
+}
+
+fn choose_ware(shopkeeper: &Object, game: &mut Game, header: &str, renderer: &mut dyn Renderer) -> Option<usize> {
+    if shopkeeper.inventory.is_empty() {
+        game.log.add("The shopkeeper has nothing left to sell.", colors::WHITE);
+        return None;
+    }
+    let options: Vec<String> = shopkeeper.inventory.iter().map(|ware| {
+        format!("{} ({} gold)", item_name(ware, game), item_value(ware))
+    }).collect();
+    menu(header, &options, Layout::of(renderer).inventory_width, renderer)
+}
+
+fn buy_item(shopkeeper_id: usize, objects: &mut [Object], game: &mut Game, ui: &mut Ui) {
+    let shopkeeper = &mut objects[shopkeeper_id];
+    let ware_id = match choose_ware(shopkeeper, game, "Press the key next to an item to buy it, or any other to cancel.\n", &mut *ui.renderer) {
+        Some(ware_id) => ware_id,
+        None => return,
+    };
+
+    let price = item_value(&shopkeeper.inventory[ware_id]);
+    let name = item_name(&shopkeeper.inventory[ware_id], game);
+    if price > game.gold {
+        game.log.add(format!("You cannot afford the {}.", name), colors::RED);
+    } else if game.inventory.len() >= INVENTORY_SIZE {
+        game.log.add(format!("Your inventory is full, cannot buy {}.", name), colors::RED);
+    } else {
+        game.gold -= price;
+        shopkeeper.gold += price;
+        game.inventory.push(shopkeeper.inventory.remove(ware_id));
+        game.log.add(format!("You bought a {} for {} gold.", name, price), colors::GOLD);
+    }
+}
+
+fn sell_item(shopkeeper_id: usize, objects: &mut [Object], game: &mut Game, ui: &mut Ui) {
+    let inventory_id = match inventory_menu(&game.inventory, game, "Press the key next to an item to sell it, or any other to cancel.\n", &mut *ui.renderer) {
+        Some(inventory_id) => inventory_id,
+        None => return,
+    };
+
+    if enchantment::is_stuck(inventory_id, game) {
+        return;
+    }
+    let shopkeeper = &mut objects[shopkeeper_id];
+    let price = sell_price(&game.inventory[inventory_id]);
+    let name = item_name(&game.inventory[inventory_id], game);
+    if price <= 0 {
+        game.log.add(format!("The shopkeeper is not interested in the {}.", name), colors::WHITE);
+    } else if price > shopkeeper.gold {
+        game.log.add(format!("The shopkeeper cannot afford the {}.", name), colors::WHITE);
+    } else if shopkeeper.inventory.len() >= INVENTORY_SIZE {
+        game.log.add("The shopkeeper has no room for more wares.", colors::WHITE);
+    } else {
+        let mut item = game.inventory.remove(inventory_id);
+        if item.equipment.is_some() {
+            item.dequip(&mut game.log);
+        }
+        game.gold += price;
+        shopkeeper.gold -= price;
+        shopkeeper.inventory.push(item);
+        game.log.add(format!("You sold a {} for {} gold.", name, price), colors::GOLD);
+    }
+}
+
+fn steal_item(shopkeeper_id: usize, objects: &mut [Object], game: &mut Game, ui: &mut Ui) {
+    let steal_bonus = objects[PLAYER].character.as_ref().map_or(0, |c| c.steal_bonus());
+    let shopkeeper = &mut objects[shopkeeper_id];
+    let ware_id = match choose_ware(shopkeeper, game, "Press the key next to an item to steal it, or any other to cancel.\n", &mut *ui.renderer) {
+        Some(ware_id) => ware_id,
+        None => return,
+    };
+
+    let name = item_name(&shopkeeper.inventory[ware_id], game);
+    if game.inventory.len() >= INVENTORY_SIZE {
+        game.log.add(format!("Your inventory is full, cannot steal {}.", name), colors::RED);
+    } else if rng().gen_range(0, 100) < STEAL_CHANCE + steal_bonus {
+        game.inventory.push(shopkeeper.inventory.remove(ware_id));
+        game.log.add(format!("You slip the {} into your pack unnoticed.", name), colors::LIGHT_GREEN);
+    } else {
+        game.log.add("The shopkeeper catches you stealing! \"Thief! You'll pay for that with your life!\"", colors::RED);
+        shopkeeper.ai = Some(Ai::Basic);
+    }
+}
+
+// Dead monsters leave behind whatever they were carrying.
+fn drop_belongings(objects: &mut Vec<Object>) {
+    let mut dropped = vec![];
+    for holder in objects.iter_mut().filter(|o| o.item == Some(Item::Corpse)) {
+        let (x, y) = holder.pos();
+        for mut item in holder.inventory.drain(..) {
+            item.set_pos(x, y);
+            dropped.push(item);
+        }
+        if holder.gold > 0 {
+            dropped.push(new_gold_pile(holder.gold, x, y));
+            holder.gold = 0;
+        }
+    }
+    objects.extend(dropped);
+}
+
+const CLASSES: &[Class] = &[Class::Warrior, Class::Mage, Class::Rogue];
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+enum Class {
+    Warrior,
+    Mage,
+    Rogue,
+}
+
+impl std::fmt::Display for Class {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Class::Warrior => write!(f, "warrior"),
+            Class::Mage => write!(f, "mage"),
+            Class::Rogue => write!(f, "rogue"),
+        }
+    }
+}
+
+impl Class {
+    pub fn description(self) -> &'static str {
+        match self {
+            Class::Warrior => "Warrior: tough and strong, starts with a sword",
+            Class::Mage => "Mage: frail, starts with a dagger and scrolls",
+            Class::Rogue => "Rogue: nimble, starts with a dagger, potions and gold",
+        }
+    }
+
+    // Base stats before attributes are applied; see `Class::character`.
+    pub fn fighter(self) -> Fighter {
+        let (hp, defense, power) = match self {
+            Class::Warrior => (100, 2, 1),
+            Class::Mage => (90, 0, 2),
+            Class::Rogue => (100, 0, 2),
+        };
+        Fighter { base_max_hp: hp, hp, xp: 0, base_defense: defense, base_power: power, on_death: DeathCallback::Player }
+    }
+
+    pub fn character(self) -> Character {
+        let (strength, dexterity, constitution, intelligence, wisdom) = match self {
+            Class::Warrior => (14, 10, 14, 8, 10),
+            Class::Mage => (8, 10, 8, 16, 14),
+            Class::Rogue => (10, 14, 10, 10, 10),
+        };
+        Character {
+            strength, dexterity, constitution, intelligence, wisdom,
+            weapons: 0,
+            magic: 0,
+            stealth: 0,
+            attribute_points: 0,
+            skill_points: 0,
+            perks: vec![],
+        }
+    }
+
+    pub fn hp_per_level(self) -> i32 {
+        match self {
+            Class::Warrior => 10,
+            Class::Mage => 4,
+            Class::Rogue => 6,
+        }
+    }
+
+    pub fn starting_kit(self) -> Vec<Object> {
+        let mut kit = match self {
+            Class::Warrior => vec![new_item(Item::Sword, 0, 0), new_item(Item::Heal, 0, 0)],
+            Class::Mage => vec![
+                new_dagger(),
+                new_item(Item::Lightning, 0, 0),
+                new_item(Item::Lightning, 0, 0),
+                new_item(Item::Confuse, 0, 0),
+            ],
+            Class::Rogue => vec![new_dagger(), new_item(Item::Heal, 0, 0), new_item(Item::Heal, 0, 0)],
+        };
+        for item in kit.iter_mut() {
+            if let Some(ref mut equipment) = item.equipment {
+                equipment.equipped = true;
+            }
+        }
+        kit
+    }
+
+    pub fn caster(self) -> Caster {
+        match self {
+            Class::Warrior => Caster::new(10),
+            Class::Mage => {
+                let mut caster = Caster::new(22);
+                caster.learn(Spell::Lightning);
+                caster
+            },
+            Class::Rogue => Caster::new(20),
+        }
+    }
+
+    pub fn starting_gold(self) -> i32 {
+        match self {
+            Class::Rogue => 50,
+            _ => 0,
+        }
+    }
+
+    // Perks offered every `PERK_LEVEL_INTERVAL` levels.
+    pub fn perks(self) -> Vec<Perk> {
+        match self {
+            Class::Warrior => vec![Perk::Toughness, Perk::WeaponMastery, Perk::Evasion],
+            Class::Mage => vec![Perk::ArcaneReservoir, Perk::Study, Perk::Lore, Perk::Toughness],
+            Class::Rogue => vec![Perk::Evasion, Perk::LightFingers, Perk::WeaponMastery, Perk::Lore],
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Attribute {
+    Strength,
+    Dexterity,
+    Constitution,
+    Intelligence,
+    Wisdom,
+}
+
+const ATTRIBUTES: &[Attribute] = &[
+    Attribute::Strength, Attribute::Dexterity, Attribute::Constitution, Attribute::Intelligence, Attribute::Wisdom,
+];
+
+impl std::fmt::Display for Attribute {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Attribute::Strength => write!(f, "Strength"),
+            Attribute::Dexterity => write!(f, "Dexterity"),
+            Attribute::Constitution => write!(f, "Constitution"),
+            Attribute::Intelligence => write!(f, "Intelligence"),
+            Attribute::Wisdom => write!(f, "Wisdom"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Skill {
+    Weapons,
+    Magic,
+    Stealth,
+}
+
+const SKILLS: &[Skill] = &[Skill::Weapons, Skill::Magic, Skill::Stealth];
+
+impl std::fmt::Display for Skill {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            Skill::Weapons => write!(f, "Weapons"),
+            Skill::Magic => write!(f, "Magic"),
+            Skill::Stealth => write!(f, "Stealth"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+enum Perk {
+    Toughness,
+    WeaponMastery,
+    Evasion,
+    ArcaneReservoir,
+    LightFingers,
+    Study,
+    Lore,
+}
+
+impl Perk {
+    pub fn description(self) -> &'static str {
+        match self {
+            Perk::Toughness => "Toughness (+20 max HP)",
+            Perk::WeaponMastery => "Weapon mastery (+2 attack)",
+            Perk::Evasion => "Evasion (+2 defense)",
+            Perk::ArcaneReservoir => "Arcane reservoir (+15 max mana)",
+            Perk::LightFingers => "Light fingers (+20% chance to steal)",
+            Perk::Study => "Study (learn a new spell)",
+            Perk::Lore => "Lore (learn an unknown potion or scroll)",
+        }
+    }
+}
+
+// D20-style modifier: +1 for every two points above 10, -1 for every two below.
+fn attribute_modifier(score: i32) -> i32 {
+    if score >= 10 {
+        (score - 10) / 2
+    } else {
+        (score - 11) / 2
+    }
+}
+
+// The player's attributes, skills and perks. Combat stats are derived from
+// these on top of the `Fighter` base values and equipment bonuses.
+#[derive(Clone, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+struct Character {
+    strength: i32,
+    dexterity: i32,
+    constitution: i32,
+    intelligence: i32,
+    wisdom: i32,
+    weapons: i32,
+    magic: i32,
+    stealth: i32,
+    attribute_points: i32,
+    skill_points: i32,
+    perks: Vec<Perk>,
+}
+
+impl Character {
+    pub fn attribute(&self, attribute: Attribute) -> i32 {
+        match attribute {
+            Attribute::Strength => self.strength,
+            Attribute::Dexterity => self.dexterity,
+            Attribute::Constitution => self.constitution,
+            Attribute::Intelligence => self.intelligence,
+            Attribute::Wisdom => self.wisdom,
+        }
+    }
+
+    pub fn skill(&self, skill: Skill) -> i32 {
+        match skill {
+            Skill::Weapons => self.weapons,
+            Skill::Magic => self.magic,
+            Skill::Stealth => self.stealth,
+        }
+    }
+
+    pub fn perk_count(&self, perk: Perk) -> i32 {
+        self.perks.iter().filter(|&&p| p == perk).count() as i32
+    }
+
+    pub fn power_bonus(&self) -> i32 {
+        attribute_modifier(self.strength) + self.weapons + 2 * self.perk_count(Perk::WeaponMastery)
+    }
+
+    pub fn defense_bonus(&self) -> i32 {
+        attribute_modifier(self.dexterity) + 2 * self.perk_count(Perk::Evasion)
+    }
+
+    pub fn max_hp_bonus(&self) -> i32 {
+        (self.constitution - 10) * 5 + 20 * self.perk_count(Perk::Toughness)
+    }
+
+    pub fn max_mana_bonus(&self) -> i32 {
+        (self.intelligence - 10) * 3 + 5 * self.magic + 15 * self.perk_count(Perk::ArcaneReservoir)
+    }
+
+    pub fn mana_regen_interval(&self) -> u32 {
+        cmp::max(1, MANA_REGEN_INTERVAL as i32 - attribute_modifier(self.wisdom)) as u32
+    }
+
+    pub fn steal_bonus(&self) -> u32 {
+        (5 * self.stealth + 20 * self.perk_count(Perk::LightFingers)) as u32
+    }
+
+    pub fn stealth_bonus(&self) -> i32 {
+        attribute_modifier(self.dexterity) + 2 * self.stealth
+    }
+}
+
+// Applies a point spent on the character screen, keeping current HP and mana
+// in step with the raised maximums.
+fn raise_attribute(attribute: Attribute, player: &mut Object) {
+    if let Some(ref mut character) = player.character {
+        character.attribute_points -= 1;
+        match attribute {
+            Attribute::Strength => character.strength += 1,
+            Attribute::Dexterity => character.dexterity += 1,
+            Attribute::Constitution => character.constitution += 1,
+            Attribute::Intelligence => character.intelligence += 1,
+            Attribute::Wisdom => character.wisdom += 1,
+        }
+    }
+    match attribute {
+        Attribute::Constitution => player.fighter.as_mut().unwrap().hp += 5,
+        Attribute::Intelligence => player.restore_mana(3),
+        _ => {},
+    }
+}
+
+fn train_skill(skill: Skill, player: &mut Object) {
+    if let Some(ref mut character) = player.character {
+        character.skill_points -= 1;
+        match skill {
+            Skill::Weapons => character.weapons += 1,
+            Skill::Magic => character.magic += 1,
+            Skill::Stealth => character.stealth += 1,
+        }
+    }
+    if skill == Skill::Magic {
+        player.restore_mana(5);
+    }
+}
+
+fn gain_perk(perk: Perk, player: &mut Object, game: &mut Game) {
+    if let Some(ref mut character) = player.character {
+        character.perks.push(perk);
+    }
+    game.log.add(format!("You gained a perk: {}.", perk.description()), colors::LIGHT_GREEN);
+    match perk {
+        Perk::Toughness => player.fighter.as_mut().unwrap().hp += 20,
+        Perk::ArcaneReservoir => player.restore_mana(15),
+        Perk::Study => learn_random_spell(player, &mut game.log),
+        Perk::Lore => reveal_random_item(game),
+        _ => {},
+    }
+}
+
+fn reveal_random_item(game: &mut Game) {
+    let unknown: Vec<Item> = game.item_knowledge.iter().filter(|k| !k.identified).map(|k| k.item).collect();
+    match rng().choose(&unknown) {
+        Some(&item) => {
+            let name = new_item(item, 0, 0).name;
+            identify_item(item, &name, game);
+        },
+        None => game.log.add("Your studies reveal nothing you did not already know.", colors::LIGHT_CYAN),
+    }
+}
+
+fn level_up(objects: &mut [Object], game: &mut Game, ui: &mut Ui) {
+    let player = &mut objects[PLAYER];
+    if gain_level(player, game) && player.level % PERK_LEVEL_INTERVAL == 0 {
+        let perks = game.class.perks();
+        let options: Vec<&str> = perks.iter().map(|perk| perk.description()).collect();
+        let mut choice = None;
+        while choice.is_none() {
+            choice = menu("Level up! Choose a perk:\n", &options, LEVEL_SCREEN_WIDTH, &mut *ui.renderer);
+        };
+        gain_perk(perks[choice.unwrap()], player, game);
+    }
+}
+
+// Raises the player a level if they have enough experience, returning whether they did.
+fn gain_level(player: &mut Object, game: &mut Game) -> bool {
+    let level_up_xp = LEVEL_UP_BASE + player.level * LEVEL_UP_FACTOR;
+    if player.fighter.as_ref().map_or(0, |f| f.xp) >= level_up_xp {
+        player.level += 1;
+        game.log.add(format!("You battle skills grow stronger! You reached level {}.", player.level), colors::YELLOW);
+
+        let hp_gain = game.class.hp_per_level();
+        let fighter = player.fighter.as_mut().unwrap();
+        fighter.xp -= level_up_xp;
+        fighter.base_max_hp += hp_gain;
+        fighter.hp += hp_gain;
+
+        if let Some(ref mut character) = player.character {
+            character.attribute_points += 1;
+            character.skill_points += 1;
+            game.log.add("You have new points to spend. Press 'c' to improve your character.", colors::YELLOW);
+        }
+        true
+    } else {
+        false
+    }
+}
+
+fn character_sheet(player: &Object, game: &Game) -> String {
+    let fighter = player.fighter.unwrap();
+    let level_up_xp = LEVEL_UP_BASE + player.level * LEVEL_UP_FACTOR;
+    let equipped = player.get_all_equipped(game);
+    let equipment_power = equipped.iter().fold(0, |sum, e| sum + e.power_bonus);
+    let equipment_defense = equipped.iter().fold(0, |sum, e| sum + e.defense_bonus);
+    let equipment_hp = equipped.iter().fold(0, |sum, e| sum + e.max_hp_bonus);
+
+    let mut sheet = format!("Character information\n\n{}, level {} {}\nExperience: {} (next level at {})\n",
+                            player.name, player.level, game.class, fighter.xp, level_up_xp);
+
+    if let Some(ref character) = player.character {
+        sheet += &format!("\nAttributes ({} points to spend)\n", character.attribute_points);
+        sheet += &format!("  STR {:2}  {:+} attack\n", character.strength, attribute_modifier(character.strength));
+        sheet += &format!("  DEX {:2}  {:+} defense\n", character.dexterity, attribute_modifier(character.dexterity));
+        sheet += &format!("  CON {:2}  {:+} max HP\n", character.constitution, (character.constitution - 10) * 5);
+        sheet += &format!("  INT {:2}  {:+} max mana\n", character.intelligence, (character.intelligence - 10) * 3);
+        sheet += &format!("  WIS {:2}  1 mana every {} turns\n", character.wisdom, character.mana_regen_interval());
+
+        sheet += &format!("\nSkills ({} points to spend)\n", character.skill_points);
+        sheet += &format!("  Weapons {}  +{} attack\n", character.weapons, character.weapons);
+        sheet += &format!("  Magic   {}  -{} spell cost, +{} max mana\n", character.magic, character.magic, 5 * character.magic);
+        sheet += &format!("  Stealth {}  +{}% steal chance, {:+} stealth\n", character.stealth, 5 * character.stealth, 2 * character.stealth);
+
+        let perks: Vec<&str> = character.perks.iter().map(|perk| perk.description()).collect();
+        sheet += &format!("\nPerks: {}\n", if perks.is_empty() { "none".into() } else { perks.join(", ") });
+
+        let perk_power = 2 * character.perk_count(Perk::WeaponMastery);
+        let perk_defense = 2 * character.perk_count(Perk::Evasion);
+        let perk_hp = 20 * character.perk_count(Perk::Toughness);
+        sheet += &format!("\nAttack {} = {} base {:+} STR {:+} weapons {:+} perks {:+} equipment\n",
+                          player.power(game), fighter.base_power, attribute_modifier(character.strength),
+                          character.weapons, perk_power, equipment_power);
+        sheet += &format!("Defense {} = {} base {:+} DEX {:+} perks {:+} equipment\n",
+                          player.defense(game), fighter.base_defense, attribute_modifier(character.dexterity),
+                          perk_defense, equipment_defense);
+        sheet += &format!("Max HP {} = {} base {:+} CON {:+} perks {:+} equipment\n",
+                          player.max_hp(game), fighter.base_max_hp, (character.constitution - 10) * 5, perk_hp, equipment_hp);
+    } else {
+        sheet += &format!("\nAttack: {}\nDefense: {}\nMax HP: {}\n", player.power(game), player.defense(game), player.max_hp(game));
+    }
+
+    if let Some(ref caster) = player.caster {
+        let spells: Vec<String> = caster.spells.iter().map(|known| known.spell.to_string()).collect();
+        sheet += &format!("Mana {}/{}\nSpells: {}\n", caster.mana, player.max_mana(), spells.join(", "));
+    }
+
+    let worn: Vec<String> = game.inventory.iter().filter(|item| item.equipment.map_or(false, |e| e.equipped)).map(|item| {
+        let condition = item.durability.map_or(String::new(), |d| format!(", {} ({}/{})", d.condition(), d.current, d.max));
+        format!("  {}: {}{}\n", item.equipment.unwrap().slot, item_name(item, game), condition)
+    }).collect();
+    if !worn.is_empty() {
+        sheet += &format!("\nEquipment\n{}", worn.concat());
+    }
+
+    sheet += &format!("\nGold: {}\n", game.gold);
+    sheet
+}
+
+// Shows the character sheet and lets the player spend attribute and skill points.
+fn character_screen(objects: &mut [Object], game: &Game, renderer: &mut dyn Renderer) {
+    #[derive(Clone, Copy)]
+    enum Improvement {
+        Attribute(Attribute),
+        Skill(Skill),
+    }
+
+    loop {
+        let player = &mut objects[PLAYER];
+        let sheet = character_sheet(player, game);
+
+        let mut choices = vec![];
+        let mut options = vec![];
+        if let Some(ref character) = player.character {
+            if character.attribute_points > 0 {
+                for &attribute in ATTRIBUTES {
+                    let score = character.attribute(attribute);
+                    choices.push(Improvement::Attribute(attribute));
+                    options.push(format!("Raise {} ({} -> {})", attribute, score, score + 1));
+                }
+            }
+            if character.skill_points > 0 {
+                for &skill in SKILLS {
+                    let rank = character.skill(skill);
+                    choices.push(Improvement::Skill(skill));
+                    options.push(format!("Train {} ({} -> {})", skill, rank, rank + 1));
+                }
+            }
+        }
+
+        match menu(&sheet, &options, CHARACTER_SCREEN_WIDTH, renderer).map(|choice| choices[choice]) {
+            Some(Improvement::Attribute(attribute)) => raise_attribute(attribute, player),
+            Some(Improvement::Skill(skill)) => train_skill(skill, player),
+            None => return,
+        }
+    }
+}
+
+// The whole level at a glance. Clicking somewhere known travels there.
+fn overview_screen(ui: &mut Ui, objects: &[Object], game: &mut Game) {
+    render_all(ui, objects, game);
+    let layout = Layout::of(&*ui.renderer);
+    clear_rect(&mut *ui.renderer, 0, 0, layout.view_width, layout.view_height);
+    ui.view.draw_overview(&mut *ui.renderer, game, objects[PLAYER].pos());
+    clear_rect(&mut *ui.renderer, 0, layout.panel_y - 1, layout.screen_width, 1);
+    ui.renderer.print(0, layout.panel_y - 1, "Click somewhere to travel there, or press any key to go back.", colors::LIGHT_YELLOW);
+    ui.renderer.flush();
+
+    loop {
+        match ui.renderer.check_for_event() {
+            Some(Event::Mouse(mouse)) if mouse.lbutton_pressed => {
+                ui.mouse = mouse;
+                let destination = ui.view.overview_tiles(mouse.cx as i32, mouse.cy as i32).into_iter().find(|&(x, y)| {
+                    let tile = &game.map[x as usize][y as usize];
+                    tile.explored && !tile.blocked
+                });
+                match destination {
+                    Some((x, y)) => travel::start(Goal::Tile(x, y), ui, objects, game),
+                    None => game.log.add("You don't know a way there.", colors::LIGHT_GREY),
+                }
+                return;
+            },
+            Some(Event::Mouse(mouse)) if mouse.rbutton_pressed => return,
+            Some(Event::Key(_)) => return,
+            _ => ui.renderer.idle(),
+        }
+    }
+}
+
+fn player_death(player: &mut Object, messages: &mut Messages) {
+    messages.add("You died!", colors::DARK_RED);
+
+    player.char = '%';
+    player.color = colors::DARK_RED;
+}
+
+fn monster_death(monster: &mut Object, messages: &mut Messages) {
+    messages.add(format!("{} is dead!", monster.name), colors::ORANGE);
+    monster.char = '%';
+    monster.color = colors::DARK_RED;
+    monster.blocks = false;
+    monster.fighter = None;
+    monster.ai = None;
+    monster.name = format!("remains of {}", monster.name);
+    monster.item = Some(Item::Corpse);
+}
+
+// Mutably borrow two *separate* elements from the given slice.
+// Panics when the indexes are equal or out of bounds.
+fn mut_two<T>(first_index: usize, second_index: usize, items: &mut[T]) -> (&mut T, &mut T) {
+    assert!(first_index != second_index);
+    let split_at_index = cmp::max(first_index, second_index);
+    let (first_slice, second_slice) = items.split_at_mut(split_at_index);
+    if first_index < second_index {
+        (&mut first_slice[first_index], &mut second_slice[0])
+    } else {
+        (&mut second_slice[0], &mut first_slice[second_index])
+    }
+}
+
+// Walking into a closed door opens it.
+fn move_by(id: usize, dx: i32, dy: i32, game: &mut Game, objects: &mut [Object]) {
+    let (x, y) = objects[id].pos();
+    if terrain::is_closed_door(&game.map[(x + dx) as usize][(y + dy) as usize]) {
+        terrain::open_door(game, x + dx, y + dy);
+    } else if !is_blocked(x + dx, y + dy, &game.map, objects) {
+        objects[id].set_pos(x + dx, y + dy);
+    }
+}
+
+fn teleport_player(x: i32, y: i32, objects: &mut [Object], game: &Game) -> Result<(), String> {
+    if !in_map(x, y, &game.map) {
+        return Err(format!("({}, {}) is off the map.", x, y));
+    }
+    if is_blocked(x, y, &game.map, objects) {
+        return Err(format!("({}, {}) is blocked.", x, y));
+    }
+    objects[PLAYER].set_pos(x, y);
+    Ok(())
+}
+
+// Monsters out to kill the player, as opposed to shopkeepers and corpses.
+fn is_hostile(object: &Object) -> bool {
+    object.fighter.is_some() && matches!(object.ai, Some(Ai::Basic) | Some(Ai::Unaware) | Some(Ai::Confused { .. }))
+}
+
+fn is_blocked(x: i32, y: i32, map: &Map, objects: &[Object]) -> bool {
+    if map[x as usize][y as usize].blocked {
+        return true;
+    }
+
+    objects.iter().any(|object| {
+        object.blocks && object.pos() == (x, y)
+    })
+}
+
+fn inventory_menu(inventory: &[Object], game: &Game, header: &str, renderer: &mut dyn Renderer) -> Option<usize> {
+    let options = if inventory.len() == 0 {
+        vec!["Inventory is empty.".into()]
+    } else {
+        inventory.iter().map(|item| { 
+            let mut notes = vec![];
+            if let Some(equipment) = item.equipment.filter(|e| e.equipped) {
+                notes.push(format!("on {}", equipment.slot));
+            }
+            notes.extend(durability::condition_note(item).map(String::from));
+            if item.container.is_some() {
+                notes.push(format!("{} items", item.inventory.len()));
+            }
+            if notes.is_empty() {
+                item_name(item, game)
+            } else {
+                format!("{} ({})", item_name(item, game), notes.join(", "))
+            }
+        }).collect()
+    };
+
+    let inventory_index = menu(header, &options, Layout::of(renderer).inventory_width, renderer);
+    if inventory.len() > 0 {
+        inventory_index
+    } else {
+        None
+    }
+}
+
+
+fn menu<T: AsRef<str>>(header: &str, options: &[T], width: i32, renderer: &mut dyn Renderer) -> Option<usize> {
+    assert!(options.len() <= 26, "Cannot have a menu with more than 26 options.");
+    let width = cmp::min(width, renderer.width());
+    let header_lines = if header.is_empty() {
+        vec![]
+    } else {
+        wrap_text(header, width)
+    };
+    let header_height = header_lines.len() as i32;
+    let height = options.len() as i32 + header_height;
+
+    let x = renderer.width() / 2 - width / 2;
+    let y = renderer.height() / 2 - height / 2;
+    clear_rect(renderer, x, y, width, height);
+    for (index, line) in header_lines.iter().enumerate() {
+        renderer.print(x, y + index as i32, line, colors::WHITE);
+    }
+
+    for (index, option_text) in options.iter().enumerate() {
+        let menu_letter = (b'a' + index as u8) as char;
+        let text = format!("({}) {}", menu_letter, option_text.as_ref());
+        renderer.print(x, y + header_height + index as i32, &text, colors::WHITE);
+    }
+
+    renderer.flush();
+    let key = renderer.wait_for_keypress();
+
+    if key.printable.is_alphabetic() {
+        let index = key.printable.to_ascii_lowercase() as usize - 'a' as usize;
+        if index < options.len() {
+            Some(index)
+        } else {
+            None
+        }
+    } else {
+        None
+    }
+}
+
+fn text_input(header: &str, width: i32, renderer: &mut dyn Renderer) -> Option<String> {
+    use tcod::input::KeyCode::{ Enter, NumPadEnter, Escape, Backspace };
+
+    let width = cmp::min(width, renderer.width());
+    let mut text = String::new();
+    loop {
+        let prompt = wrap_text(&format!("{}\n\n> {}_", header, text), width);
+        let height = prompt.len() as i32;
+
+        let x = renderer.width() / 2 - width / 2;
+        let y = renderer.height() / 2 - height / 2;
+        clear_rect(renderer, x, y, width, height);
+        for (index, line) in prompt.iter().enumerate() {
+            renderer.print(x, y + index as i32, line, colors::WHITE);
+        }
+
+        renderer.flush();
+        let key = renderer.wait_for_keypress();
+        match key.code {
+            Enter | NumPadEnter => return Some(text),
+            Escape => return None,
+            Backspace => {
+                text.pop();
+            },
+            _ => {
+                let c = key.printable;
+                if (c.is_ascii_graphic() || c == ' ') && text.len() < TEXT_INPUT_MAX_LENGTH {
+                    text.push(c);
+                }
+            }
+        }
+    }
+}
+
+fn render_bar(renderer: &mut dyn Renderer, x: i32, y: i32, total_width: i32, name: &str, value: i32, maximum: i32, bar_color: Color, back_color: Color) {
+    let bar_width = (value as f32 / maximum as f32 * total_width as f32) as i32;
+    for dx in 0..total_width {
+        let color = if dx < bar_width { bar_color } else { back_color };
+        renderer.set_background(x + dx, y, color);
+    }
+    print_centered(renderer, x + total_width / 2, y, &format!("{}: {}/{}", name, value, maximum), colors::WHITE);
+}
+
+fn update_fov(ui: &mut Ui, objects: &mut [Object], game: &mut Game) {
+    for (x, y) in game.changed_tiles.drain(..) {
+        if in_map(x, y, &game.map) {
+            ui.fov.set(x, y, &game.map[x as usize][y as usize]);
+            ui.view.mark_changed(x, y);
+        }
+    }
+    ui.fov.update(objects, game);
+    for &(x, y) in ui.fov.visible_tiles() {
+        game.map[x as usize][y as usize].explored = true;
+    }
+    for object in objects.iter_mut().filter(|object| object.item.is_some()) {
+        if ui.fov.is_in_fov(object.x, object.y) {
+            object.seen = true;
+        }
+    }
+}
+
+fn reveal_map(game: &mut Game) {
+    for tile in game.map.iter_mut().flat_map(|column| column.iter_mut()) {
+        tile.explored = true;
+    }
+}
+
+fn render_all(ui: &mut Ui, objects: &[Object], game: &Game) {
+    let fov = &ui.fov;
+    let renderer = &mut *ui.renderer;
+    let layout = Layout::of(renderer);
+
+    // The view covers everything above the panel, so only the panel needs
+    // clearing.
+    ui.view.update(fov, objects, game, &layout);
+    ui.view.draw(renderer);
+    clear_rect(renderer, 0, layout.panel_y, layout.screen_width, layout.screen_height - layout.panel_y);
+
+    let hp = objects[PLAYER].fighter.map_or(0, |f| f.hp);
+    let max_hp = objects[PLAYER].max_hp(game);
+    render_bar(renderer, 1, layout.panel_y + 1, layout.bar_width, "HP", hp, max_hp, colors::LIGHT_RED, colors::DARKER_RED);
+    if let Some(ref caster) = objects[PLAYER].caster {
+        let max_mana = objects[PLAYER].max_mana();
+        render_bar(renderer, 1, layout.panel_y + 2, layout.bar_width, "MP", caster.mana, max_mana, colors::LIGHT_BLUE, colors::DARKER_BLUE);
+    }
+    renderer.print(1, layout.panel_y + 3, &format!("Dungeon level: {}", game.dungeon_level), colors::WHITE);
+    renderer.print(1, layout.panel_y + 4, &format!("Gold: {}", game.gold), colors::WHITE);
+
+    renderer.print(1, layout.panel_y, &get_names_under_mouse(ui.mouse, objects, game, fov, &ui.view), colors::LIGHT_GREY);
+
+    let mut y = layout.msg_height;
+    for &(ref msg, color) in game.log.iter().rev() {
+        let lines = wrap_text(msg, layout.msg_width);
+        y -= lines.len() as i32;
+        if y < 0 {
+            break;
+        }
+        for (index, line) in lines.iter().enumerate() {
+            renderer.print(layout.msg_x, layout.panel_y + y + index as i32, line, color);
+        }
+    }
+}
+
+fn player_move_or_attack(dx: i32, dy: i32, game: &mut Game, objects: &mut [Object], ui: &mut Ui) {
+    let x = objects[PLAYER].x + dx;
+    let y = objects[PLAYER].y + dy;
+
+    let target_id = objects.iter().position(|object| {
+        object.fighter.is_some() && object.pos() == (x, y)
+    });
+    let chest_id = objects.iter().position(|object| {
+        object.container.is_some() && object.item.is_none() && object.pos() == (x, y)
+    });
+    if let Some(chest_id) = chest_id {
+        containers::open_chest(chest_id, objects, game, ui);
+        return;
+    }
+    let anvil_id = objects.iter().position(|object| {
+        object.anvil.is_some() && object.pos() == (x, y)
+    });
+    if let Some(anvil_id) = anvil_id {
+        durability::use_anvil(anvil_id, objects, game);
+        return;
+    }
+
+    match target_id {
+        Some(target_id) if objects[target_id].ai == Some(Ai::Shopkeeper) => {
+            visit_shop(target_id, objects, game, ui);
+        },
+        Some(target_id) => {
+            let (player, target) = mut_two(PLAYER, target_id, objects);
+            player.attack(game, target);
+            stealth::make_noise(ui, stealth::ATTACK_NOISE);
+        },
+        None if terrain::wielding_pickaxe(game) && terrain::is_diggable(&game.map, x, y)
+            && !terrain::is_closed_door(&game.map[x as usize][y as usize]) => {
+            if terrain::dig(game, x, y) {
+                game.log.add("You break through.", colors::LIGHT_SEPIA);
+            } else {
+                game.log.add("You dig into the rock.", colors::LIGHT_GREY);
+            }
+            durability::wear(&mut objects[PLAYER], Wear::Pickaxe, game);
+            stealth::make_noise(ui, stealth::ATTACK_NOISE);
+        },
+        None => {
+            if terrain::is_closed_door(&game.map[x as usize][y as usize]) {
+                game.log.add("You open the door.", colors::LIGHT_GREY);
+            }
+            let from = objects[PLAYER].pos();
+            move_by(PLAYER, dx, dy, game, objects);
+            if objects[PLAYER].pos() != from {
+                stealth::make_noise(ui, stealth::walking_noise(&objects[PLAYER], game));
+            }
+        }
+    }
+}
+
+fn get_names_under_mouse(mouse: Mouse, objects: &[Object], game: &Game, fov_map: &Vision, view: &MapView) -> String {
+    let (x, y) = match view.to_world(mouse.cx as i32, mouse.cy as i32) {
+        Some(position) => position,
+        None => return String::new(),
+    };
+    let names = objects
+        .iter()
+        .filter(|obj| { obj.pos() == (x, y) && fov_map.is_in_fov(obj.x, obj.y) })
+        .map(|obj| item_name(obj, game))
+        .collect::<Vec<_>>();
+
+    names.join(", ")
+}
+
+fn handle_keys(key: Key, ui: &mut Ui, game: &mut Game, objects: &mut Vec<Object>) -> PlayerAction {
+
+    use tcod::input::Key;
+    use tcod::input::KeyCode::*;
+
+    let player_alive = objects[PLAYER].alive;
+    match (key, player_alive) {
+        (Key { shift: true, .. }, true) if key_direction(key).is_some() => {
+            let (dx, dy) = key_direction(key).unwrap();
+            travel::start(Goal::Run(dx, dy), ui, objects, game);
+            PlayerAction::DidntTakeTurn
+        },
+        (Key { code: Up, .. }, true) | (Key { code: NumPad8, .. }, true) => {
+            player_move_or_attack(0, -1, game, objects, ui);
+            PlayerAction::TookTurn
+        },
+        (Key { code: Down, .. }, true) | (Key { code: NumPad2, .. }, true) => {
+            player_move_or_attack(0, 1, game, objects, ui);
+            PlayerAction::TookTurn
+        },
+        (Key { code: Left, .. }, true) | (Key { code: NumPad4, .. }, true) => {
+            player_move_or_attack(-1, 0, game, objects, ui);
+            PlayerAction::TookTurn
+        },
+        (Key { code: Right, .. }, true) | (Key { code: NumPad6, .. }, true) => {
+            player_move_or_attack(1, 0, game, objects, ui);
+            PlayerAction::TookTurn
+        },
+        (Key { code: Home, .. }, true) | (Key { code: NumPad7, .. }, true) => {
+            player_move_or_attack(-1, -1, game, objects, ui);
+            PlayerAction::TookTurn
+        },
+        (Key { code: PageUp, .. }, true) | (Key { code: NumPad9, .. }, true) => {
+            player_move_or_attack(1, -1, game, objects, ui);
+            PlayerAction::TookTurn
+        },
+        (Key { code: End, .. }, true) | (Key { code: NumPad1, .. }, true) => {
+            player_move_or_attack(-1, 1, game, objects, ui);
+            PlayerAction::TookTurn
+        },
+        (Key { code: PageDown, .. }, true) | (Key { code: NumPad3, .. }, true) => {
+            player_move_or_attack(1, 1, game, objects, ui);
+            PlayerAction::TookTurn
+        },
+        (Key { code: NumPad5, .. }, true) => {
+            PlayerAction::TookTurn
+        },
+        (Key { printable: '>', .. }, true) => {
+            let player_on_stairs = objects.iter().any(|object| {
+                object.pos() == objects[PLAYER].pos() && object.name == "stairs"
+            });
+            if player_on_stairs {
+                next_level(ui, objects, game);
+            } else {
+                match travel::known_stairs(objects, game) {
+                    Some((x, y)) => travel::start(Goal::Tile(x, y), ui, objects, game),
+                    None => game.log.add("You haven't found the stairs yet.", colors::LIGHT_GREY),
+                }
+            }
+            PlayerAction::DidntTakeTurn
+        },
+        (Key { printable: 't', .. }, true) => {
+            lighting::toggle_light(objects, game);
+            PlayerAction::TookTurn
+        },
+        (Key { printable: 'x', .. }, true) => {
+            travel::start(Goal::Explore, ui, objects, game);
+            PlayerAction::DidntTakeTurn
+        },
+        (Key { printable: 'g', .. }, true) => {
+            pick_up(game, objects, ui)
+        },
+        (Key { printable: 'd', .. }, true) => {
+            let inventory_index = inventory_menu(&game.inventory, game, "Press the key next to an item to drop it, or any other to cancel.\n", &mut *ui.renderer);
+            if let Some(inventory_index) = inventory_index {
+                drop_item(inventory_index, game, objects);
+            }
+            PlayerAction::TookTurn
+        },
+        (Key { printable: 'i', .. }, true) => {
+            let inventory_index = inventory_menu(&game.inventory, game, "Press the key next to an item to use it, or any other key to cancel.\n", &mut *ui.renderer);
+            if let Some(inventory_index) = inventory_index {
+                use_item(inventory_index, game, objects, ui);
+            }
+            PlayerAction::TookTurn
+        },
+        (Key { printable: 'z', .. }, true) => {
+            cast_known_spell(objects, game, ui)
+        },
+        (Key { printable: 'n', .. }, true) => {
+            let inventory_index = inventory_menu(&game.inventory, game, "Press the key next to an item to call it something, or any other key to cancel.\n", &mut *ui.renderer);
+            if let Some(inventory_index) = inventory_index {
+                call_item(inventory_index, game, &mut *ui.renderer);
+            }
+            PlayerAction::DidntTakeTurn
+        },
+        (Key { printable: 'c', .. }, true) => {
+            character_screen(objects, game, &mut *ui.renderer);
+            PlayerAction::DidntTakeTurn
+        },
+        (Key { printable: 'l', .. }, true) => {
+            look::look(ui, objects, game);
+            PlayerAction::DidntTakeTurn
+        },
+        (Key { printable: 'm', .. }, true) => {
+            overview_screen(ui, objects, game);
+            PlayerAction::DidntTakeTurn
+        },
+        (Key { printable: '`', .. }, _) => {
+            console::open(ui, objects, game);
+            PlayerAction::DidntTakeTurn
+        },
+        (Key { code: Enter, alt: true, .. }, _) => {
+            ui.renderer.toggle_fullscreen();
+            PlayerAction::DidntTakeTurn
+        },
+        (Key { code: Escape, .. }, _) => return PlayerAction::Exit,
+        _ => PlayerAction::DidntTakeTurn,
+    }
+}
+
+// The step a movement key stands for, arrows and numpad alike.
+fn key_direction(key: Key) -> Option<(i32, i32)> {
+    use tcod::input::KeyCode::*;
+    match key.code {
+        Up | NumPad8 => Some((0, -1)),
+        Down | NumPad2 => Some((0, 1)),
+        Left | NumPad4 => Some((-1, 0)),
+        Right | NumPad6 => Some((1, 0)),
+        Home | NumPad7 => Some((-1, -1)),
+        PageUp | NumPad9 => Some((1, -1)),
+        End | NumPad1 => Some((-1, 1)),
+        PageDown | NumPad3 => Some((1, 1)),
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PlayerAction {
+    TookTurn,
+    DidntTakeTurn,
+    Exit,
+}
+
+fn create_room(room: Rect, map: &mut Map) {
+    for x in (room.x1 + 1)..room.x2 {
+        for y in (room.y1 + 1)..room.y2 {
+            map[x as usize][y as usize] = Tile::empty();
+        }
+    }
+}
+
+fn create_h_tunnel(x1: i32, x2: i32, y: i32, map: &mut Map) {
+    for x in cmp::min(x1, x2)..(cmp::max(x1, x2) + 1) {
+        map[x as usize][y as usize] = Tile::empty();
+    }
+}
+
+fn create_v_tunnel(y1: i32, y2: i32, x: i32, map: &mut Map) {
+    for y in cmp::min(y1, y2)..(cmp::max(y1, y2) + 1) {
+        map[x as usize][y as usize] = Tile::empty();
+    }
+}
+
+fn item_chances(level: u32) -> Vec<Weighted<Item>> {
+    vec![
+        Weighted { weight: 70, item: Item::Heal },
+        Weighted { 
+            weight: from_dungeon_level(&[Transition { level: 2, value: 25 }], level),
+            item: Item::Lightning 
+        },
+        Weighted { 
+            weight: 10, 
+            item: Item::Confuse 
+        },
+        Weighted { 
+            weight: from_dungeon_level(&[Transition { level: 5, value: 25 }], level), 
+            item: Item::Fireball 
+        },
+        Weighted {
+            weight: from_dungeon_level(&[Transition { level: 4, value: 15 }], level),
+            item: Item::PoisonCloud
+        },
+        Weighted {
+            weight: from_dungeon_level(&[Transition { level: 2, value: 10 }], level),
+            item: Item::Identify
+        },
+        Weighted {
+            weight: from_dungeon_level(&[Transition { level: 3, value: 8 }], level),
+            item: Item::Enchant
+        },
+        Weighted {
+            weight: from_dungeon_level(&[Transition { level: 2, value: 6 }], level),
+            item: Item::RemoveCurse
+        },
+        Weighted {
+            weight: from_dungeon_level(&[Transition { level: 3, value: 5 }], level),
+            item: Item::Sword 
+        },
+        Weighted {
+            weight: from_dungeon_level(&[Transition { level: 6, value: 15 }], level),
+            item: Item::Shield
+        },
+        Weighted {
+            weight: from_dungeon_level(&[Transition { level: 2, value: 8 }], level),
+            item: Item::Lantern
+        },
+        Weighted {
+            weight: from_dungeon_level(&[Transition { level: 2, value: 5 }], level),
+            item: Item::Pickaxe
+        },
+        Weighted {
+            weight: 5,
+            item: Item::Bag
+        },
+        Weighted {
+            weight: from_dungeon_level(&[Transition { level: 2, value: 6 }], level),
+            item: Item::RepairKit
+        },
+        Weighted {
+            weight: from_dungeon_level(&[Transition { level: 2, value: 3 }], level),
+            item: Item::Spellbook(Spell::Heal)
+        },
+        Weighted {
+            weight: from_dungeon_level(&[Transition { level: 2, value: 3 }], level),
+            item: Item::Spellbook(Spell::Confuse)
+        },
+        Weighted {
+            weight: from_dungeon_level(&[Transition { level: 3, value: 3 }], level),
+            item: Item::Spellbook(Spell::Lightning)
+        },
+        Weighted {
+            weight: from_dungeon_level(&[Transition { level: 5, value: 3 }], level),
+            item: Item::Spellbook(Spell::Fireball)
+        },
+        Weighted {
+            weight: from_dungeon_level(&[Transition { level: 4, value: 3 }], level),
+            item: Item::Spellbook(Spell::PoisonCloud)
+        },
+        Weighted {
+            weight: from_dungeon_level(&[Transition { level: 3, value: 3 }], level),
+            item: Item::Spellbook(Spell::Dig)
+        }
+    ]
+}
+
+fn new_item(item: Item, x: i32, y: i32) -> Object {
+    match item {
+        Item::Heal => {
+            let mut object = Object::new(x, y, '!', "healing potion", colors::VIOLET, false);
+            object.item = Some(Item::Heal);
+            object
+        },
+        Item::Lightning => {
+            let mut object = Object::new(x, y, '?', "scroll of lightning bolt", colors::LIGHT_YELLOW, false);
+            object.item = Some(Item::Lightning);
+            object
+        },
+        Item::Confuse => {
+            let mut object = Object::new(x, y, '?', "scroll of confusion", colors::LIGHT_YELLOW, false);
+            object.item = Some(Item::Confuse);
+            object
+        },
+        Item::Fireball => {
+            let mut object = Object::new(x, y, '?', "scroll of fireball", colors::LIGHT_YELLOW, false);
+            object.item = Some(Item::Fireball);
+            object
+        },
+        Item::PoisonCloud => {
+            let mut object = Object::new(x, y, '?', "scroll of poison cloud", colors::LIGHT_YELLOW, false);
+            object.item = Some(Item::PoisonCloud);
+            object
+        },
+        Item::Identify => {
+            let mut object = Object::new(x, y, '?', "scroll of identify", colors::LIGHT_YELLOW, false);
+            object.item = Some(Item::Identify);
+            object
+        },
+        Item::Enchant => {
+            let mut object = Object::new(x, y, '?', "scroll of enchant equipment", colors::LIGHT_YELLOW, false);
+            object.item = Some(Item::Enchant);
+            object
+        },
+        Item::RemoveCurse => {
+            let mut object = Object::new(x, y, '?', "scroll of remove curse", colors::LIGHT_YELLOW, false);
+            object.item = Some(Item::RemoveCurse);
+            object
+        },
+        Item::Shield => {
+            let mut object = Object::new(x, y, '[', "shield", colors::DARKER_ORANGE, false);
+            object.item = Some(Item::Shield);
+            object.equipment = Some(Equipment { equipped: false, slot: Slot::LeftHand, power_bonus: 0, defense_bonus: 2, max_hp_bonus: 0 });
+            object.durability = Some(Durability::new(durability::ARMOR_DURABILITY));
+            object
+        },
+        Item::Sword => {
+            let mut object = Object::new(x, y, '/', "sword", colors::SKY, false);
+            object.item = Some(Item::Sword);
+            object.equipment = Some(Equipment { equipped: false, slot: Slot::RightHand, power_bonus: 4, defense_bonus: 0, max_hp_bonus: 0 });
+            object.durability = Some(Durability::new(durability::WEAPON_DURABILITY));
+            object
+        },
+        Item::Lantern => {
+            let mut object = Object::new(x, y, '(', "lantern", colors::YELLOW, false);
+            object.item = Some(Item::Lantern);
+            object.equipment = Some(Equipment { equipped: false, slot: Slot::Belt, power_bonus: 0, defense_bonus: 0, max_hp_bonus: 0 });
+            object.light = Some(Light { radius: lighting::LANTERN_RADIUS, lit: true, fuel: Some(lighting::LANTERN_FUEL) });
+            object
+        },
+        Item::Pickaxe => {
+            let mut object = Object::new(x, y, '(', "pickaxe", colors::LIGHT_SEPIA, false);
+            object.item = Some(Item::Pickaxe);
+            object.equipment = Some(Equipment { equipped: false, slot: Slot::RightHand, power_bonus: 1, defense_bonus: 0, max_hp_bonus: 0 });
+            object.durability = Some(Durability::new(durability::PICKAXE_DURABILITY));
+            object
+        },
+        Item::Bag => {
+            let mut object = Object::new(x, y, '(', "bag", colors::SEPIA, false);
+            object.item = Some(Item::Bag);
+            object.container = Some(Container { capacity: containers::BAG_CAPACITY, locked: false, trap: None });
+            object
+        },
+        Item::RepairKit => {
+            let mut object = Object::new(x, y, ']', "repair kit", colors::LIGHT_GREY, false);
+            object.item = Some(Item::RepairKit);
+            object
+        },
+        Item::Spellbook(spell) => {
+            let mut object = Object::new(x, y, '#', &format!("spellbook of {}", spell), colors::LIGHT_PURPLE, false);
+            object.item = Some(Item::Spellbook(spell));
+            object
+        },
+        _ => unreachable!(),
+    }
+}
+
+// An item found on a dungeon level: equipment may be better or worse made than
+// usual, and may be magic.
+fn generate_item(item: Item, level: u32, x: i32, y: i32) -> Object {
+    let mut object = new_item(item, x, y);
+    enchantment::roll(&mut object, level);
+    object
+}
+
+// The items that can be created by name, from the wizard console or the save
+// tool. Corpses and gold only come from monsters and the dungeon.
+fn named_items() -> Vec<Item> {
+    let mut items = vec![Item::Heal, Item::Lightning, Item::Fireball, Item::Confuse, Item::PoisonCloud, Item::Identify, Item::Enchant, Item::RemoveCurse, Item::Sword, Item::Shield, Item::Lantern, Item::Pickaxe, Item::Bag, Item::RepairKit];
+    items.extend(SPELLS.iter().map(|&spell| Item::Spellbook(spell)));
+    items
+}
+
+fn item_names() -> Vec<String> {
+    named_items().into_iter().map(|item| new_item(item, 0, 0).name).collect()
+}
+
+fn named_item(name: &str, x: i32, y: i32) -> Option<Object> {
+    named_items().into_iter().map(|item| new_item(item, x, y)).find(|item| item.name == name)
+}
+
+fn new_dagger() -> Object {
+    let mut dagger = Object::new(0, 0 ,'-', "dagger", colors::SKY, false);
+    dagger.item = Some(Item::Sword);
+    dagger.equipment = Some(Equipment {
+        equipped: false,
+        slot: Slot::LeftHand,
+        max_hp_bonus: 0,
+        defense_bonus: 0,
+        power_bonus: 2
+    });
+    dagger.durability = Some(Durability::new(durability::WEAPON_DURABILITY));
+    dagger
+}
+
+fn new_gold_pile(amount: i32, x: i32, y: i32) -> Object {
+    let mut pile = Object::new(x, y, '$', &format!("{} gold pieces", amount), colors::GOLD, false);
+    pile.item = Some(Item::Gold);
+    pile.gold = amount;
+    pile
+}
+
+fn new_shopkeeper(level: u32, x: i32, y: i32) -> Object {
+    let mut shopkeeper = Object::new(x, y, '@', "shopkeeper", colors::LIGHT_BLUE, true);
+    shopkeeper.fighter = Some(Fighter { base_max_hp: 100, hp: 100, xp: 500, base_defense: 4, base_power: 10, on_death: DeathCallback::Monster });
+    shopkeeper.ai = Some(Ai::Shopkeeper);
+    shopkeeper.alive = true;
+    shopkeeper.gold = 100 + 50 * level as i32;
+
+    let chances = &mut item_chances(level);
+    let item_choice = WeightedChoice::new(chances);
+    let num_wares = rng().gen_range(SHOP_MIN_WARES, SHOP_MAX_WARES + 1);
+    for _ in 0..num_wares {
+        let ware = generate_item(item_choice.ind_sample(&mut rng()), level, x, y);
+        shopkeeper.inventory.push(ware);
+    }
+    shopkeeper
+}
+
+const MONSTERS: &[&str] = &["orc", "troll", "ogre"];
+
+fn new_monster(name: &str, x: i32, y: i32) -> Option<Object> {
+    let mut monster = match name {
+        "orc" => {
+            let mut orc = Object::new(x, y, 'o', "orc", colors::DESATURATED_GREEN, true);
+            orc.fighter = Some(Fighter { base_max_hp: 20, hp: 20, xp: 35, base_defense: 0, base_power: 4, on_death: DeathCallback::Monster });
+            // Some carry torches, and can be seen coming.
+            if rng().gen_range(0, 4) == 0 {
+                orc.light = Some(Light { radius: 5, lit: true, fuel: None });
+            }
+            orc
+        },
+        "troll" => {
+            let mut troll = Object::new(x, y, 'T', "troll", colors::DARKER_GREEN, true);
+            troll.fighter = Some(Fighter { base_max_hp: 30, hp: 30, xp: 100, base_defense: 2, base_power: 8, on_death: DeathCallback::Monster });
+            troll
+        },
+        "ogre" => {
+            let mut ogre = Object::new(x, y, 'O', "ogre", colors::WHITE, true);
+            ogre.fighter = Some(Fighter { base_max_hp: 70, hp: 70, xp: 250, base_defense: 2, base_power: 12, on_death: DeathCallback::Monster });
+            ogre
+        },
+        _ => return None,
+    };
+    monster.ai = Some(Ai::Unaware);
+    monster.alive = true;
+    Some(monster)
+}
+
+fn place_objects(level: u32, room: Rect, map: &Map, objects: &mut Vec<Object>) {
+
+    let max_monsters = from_dungeon_level(&[
+        Transition { level: 1, value: 2 },
+        Transition { level: 3, value: 3 },
+        Transition { level: 5, value: 5 },
+    ], level);
+
+    let num_monsters = rng().gen_range(0, max_monsters + 1);
+
+    let troll_chance = from_dungeon_level(&[
+        Transition { level: 3, value: 15 },
+        Transition { level: 4, value: 100 },
+        Transition { level: 5, value: 25 },
+    ], level);
+    
+    let ogre_chance = from_dungeon_level(&[
+        Transition { level: 4, value: 10 },
+        Transition { level: 5, value: 100 },
+    ], level);
+
+    let monster_chances = &mut [
+        Weighted { weight: 80, item: "orc" },
+        Weighted { weight: troll_chance, item: "troll" },
+        Weighted { weight: ogre_chance, item: "ogre" },
+    ];
+    let monster_choice = WeightedChoice::new(monster_chances);
+
+    for _ in 0..num_monsters {
+        let x = rng().gen_range(room.x1 + 1, room.x2);
+        let y = rng().gen_range(room.y1 + 1, room.y2);
+        if !is_blocked(x, y, map, objects) {
+            if let Some(monster) = new_monster(monster_choice.ind_sample(&mut rng()), x, y) {
+                objects.push(monster);
+            }
+        }
+    }
+
+    let chances = &mut item_chances(level);
+    let item_choice = WeightedChoice::new(chances);
+
+    let max_items = from_dungeon_level(&[
+        Transition { level: 1, value: 1 },
+        Transition { level: 3, value: 2 },
+        Transition { level: 5, value: 3 },
+    ], level);
+
+    let num_items = rng().gen_range(0, max_items + 1);
+    for _ in 0..num_items {
+        let x = rng().gen_range(room.x1 + 1, room.x2);
+        let y = rng().gen_range(room.y1 + 1, room.y2);
+        if !is_blocked(x, y, map, objects) {
+            let item = generate_item(item_choice.ind_sample(&mut rng()), level, x, y);
+            objects.push(item);
+        }
+    }
+
+    if rng().gen_range(0, 100) < GOLD_CHANCE {
+        let x = rng().gen_range(room.x1 + 1, room.x2);
+        let y = rng().gen_range(room.y1 + 1, room.y2);
+        if !is_blocked(x, y, map, objects) {
+            let amount = rng().gen_range(1, 10 + 5 * level as i32);
+            objects.push(new_gold_pile(amount, x, y));
+        }
+    }
+
+    containers::place_chest(level, room, map, objects);
+    durability::place_anvil(level, room, map, objects);
+}
+
+fn move_towards(id: usize, target_x: i32, target_y: i32, game: &mut Game, objects: &mut [Object]) {
+    let dx = target_x - objects[id].x;
+    let dy = target_y - objects[id].y;
+    let distance = ((dx.pow(2) + dy.pow(2)) as f32).sqrt();
+
+    let dx = (dx as f32 / distance).round() as i32;
+    let dy = (dy as f32 / distance).round() as i32;
+    move_by(id, dx, dy, game, objects);
+}
+
+fn ai_take_turn(monster_id: usize, game: &mut Game, objects: &mut [Object], fov_map: &Vision, noise: &Noise) {
+    if let Some(ai) = objects[monster_id].ai.take() {
+        let new_ai = match ai {
+            Ai::Basic => ai_basic(monster_id, game, objects, fov_map, noise),
+            Ai::Unaware => ai_unaware(monster_id, game, objects, fov_map, noise),
+            Ai::Confused { previous_ai, num_turns } => ai_confused(monster_id, game, objects, previous_ai, num_turns),
+            Ai::Shopkeeper => Ai::Shopkeeper,
+        };
+        // Thorns can kill a monster in its own attack, and the dead stay dead.
+        if objects[monster_id].alive {
+            objects[monster_id].ai = Some(new_ai);
+        }
+    }
+}
+
+fn ai_basic(monster_id: usize, game: &mut Game, objects: &mut [Object], fov_map: &Vision, noise: &Noise) -> Ai {
+    let (monster_x, monster_y) = objects[monster_id].pos();
+    if fov_map.notices_player(monster_x, monster_y) {
+        if objects[monster_id].distance_to(&objects[PLAYER]) >= 2.0 {
+            let (player_x, player_y) = objects[PLAYER].pos();
+            move_towards(monster_id, player_x, player_y, game, objects);
+        } else if objects[PLAYER].fighter.map_or(false, |f| f.hp > 0) {
+            let (monster, player) = mut_two(monster_id, PLAYER, objects);
+            monster.attack(game, player);
+        }
+    } else if noise.heard(monster_x, monster_y) > 0 {
+        // Out of sight but not out of earshot: head for the sound.
+        let (player_x, player_y) = objects[PLAYER].pos();
+        move_towards(monster_id, player_x, player_y, game, objects);
+    }
+    Ai::Basic
+}
+
+fn ai_unaware(monster_id: usize, game: &mut Game, objects: &mut [Object], fov_map: &Vision, noise: &Noise) -> Ai {
+    let (monster_x, monster_y) = objects[monster_id].pos();
+    if stealth::perceives(monster_x, monster_y, objects, game, fov_map, noise) {
+        if fov_map.is_in_fov(monster_x, monster_y) {
+            game.log.add(format!("The {} notices you!", objects[monster_id].name), colors::ORANGE);
+        }
+        Ai::Basic
+    } else {
+        Ai::Unaware
+    }
+}
+
+fn ai_confused(monster_id: usize, game: &mut Game, objects: &mut [Object], previous_ai: Box<Ai>, num_turns: i32) -> Ai {
+    if num_turns >= 0 {
+        move_by(monster_id, 
+                rng().gen_range(-1, 2),
+                rng().gen_range(-1, 2),
+                game,
+                objects);
+        Ai::Confused { previous_ai: previous_ai, num_turns: num_turns-1 }
+    } else {
+        game.log.add(format!("The {} is no longer confused!", objects[monster_id].name), colors::RED);
+        *previous_ai
+    }
+}
+
+// Deeper levels sprawl further than fits on the screen.
+fn level_size(level: u32) -> (i32, i32) {
+    let width = from_dungeon_level(&[
+        Transition { level: 1, value: 80 },
+        Transition { level: 3, value: 100 },
+        Transition { level: 5, value: 120 },
+        Transition { level: 8, value: 150 },
+    ], level);
+    let height = from_dungeon_level(&[
+        Transition { level: 1, value: 43 },
+        Transition { level: 3, value: 55 },
+        Transition { level: 5, value: 70 },
+        Transition { level: 8, value: 80 },
+    ], level);
+    (width as i32, height as i32)
+}
+
+fn map_width(map: &Map) -> i32 {
+    map.len() as i32
+}
+
+fn map_height(map: &Map) -> i32 {
+    map.first().map_or(0, |column| column.len() as i32)
+}
+
+fn in_map(x: i32, y: i32, map: &Map) -> bool {
+    x >= 0 && y >= 0 && x < map_width(map) && y < map_height(map)
+}
+
+fn make_map(level: u32, objects: &mut Vec<Object>) -> Map {
+    let (width, height) = level_size(level);
+    let mut map = vec![vec![Tile::wall(); height as usize]; width as usize];
+
+    // Bigger maps get proportionally more tries at fitting a room in.
+    let (first_width, first_height) = level_size(1);
+    let max_rooms = MAX_ROOMS * width * height / (first_width * first_height);
+    let mut rooms = vec![];
+    for _ in 0..max_rooms {
+        let w = rng().gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
+        let h = rng().gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
+        let x = rng().gen_range(0, width - w);
+        let y = rng().gen_range(0, height - h);
+        let new_room = Rect::new(x, y, w, h);
+        let failed = rooms.iter().any(|other_room| new_room.intersects_with(other_room));
+        if !failed {
+            create_room(new_room, &mut map);
+            lighting::light_room(level, new_room, &mut map, objects);
+            effects::furnish_room(level, new_room, &mut map);
+            let (new_x, new_y) = new_room.center();
+            if rooms.is_empty() {
+                objects[PLAYER].set_pos(new_x, new_y);
+            } else {
+                let (prev_x, prev_y) = rooms[rooms.len() - 1].center();
+                if rng().gen() {
+                    create_h_tunnel(prev_x, new_x, prev_y, &mut map);
+                    create_v_tunnel(prev_y, new_y, new_x, &mut map);
+                } else {
+                    create_v_tunnel(prev_y, new_y, prev_x, &mut map);
+                    create_h_tunnel(prev_x, new_x, new_y, &mut map);
+                }
+            }
+            // Placed after the player so that nothing spawns on top of them.
+            place_objects(level, new_room, &map, objects);
+            rooms.push(new_room);
+        }
+    }
+
+    // One of the rooms between the first and the last may be turned into a shop.
+    let shop_chance = from_dungeon_level(&[Transition { level: 2, value: 40 }], level);
+    if rooms.len() > 2 && rng().gen_range(0, 100) < shop_chance {
+        let shop = rooms[rng().gen_range(1, rooms.len() - 1)];
+        objects.retain(|object| !shop.contains(object.x, object.y));
+        let (shop_x, shop_y) = shop.center();
+        objects.push(new_shopkeeper(level, shop_x, shop_y));
+    }
+
+    let (last_room_x, last_room_y) = rooms[rooms.len() - 1].center();
+    let mut stairs = Object::new(last_room_x, last_room_y, '>', "stairs", colors::WHITE, false);
+    stairs.always_visible = true;
+    objects.push(stairs);
+
+    terrain::place_doors(&rooms, &mut map);
+    terrain::crack_walls(&mut map);
+    map
+}
+
+struct Ui {
+    renderer: Box<dyn Renderer>,
+    // Reset for each level's map by `initialize_fov`.
+    fov: Vision,
+    mouse: Mouse,
+    console: Option<Console>,
+    view: MapView,
+    travel: Option<Travel>,
+    // The loudest noise the player has made this turn.
+    noise: i32,
+}
+
+impl Ui {
+    // A UI without a window, answering prompts from `keys`.
+    fn headless(keys: Rc<RefCell<VecDeque<Key>>>) -> Ui {
+        Ui {
+            renderer: Box::new(ScriptedRenderer::new(keys)),
+            fov: Vision::new(FovAlgorithm::Basic),
+            mouse: Default::default(),
+            console: None,
+            view: MapView::new(&Settings::default()),
+            travel: None,
+            noise: 0,
+        }
+    }
+}
+
+#[derive(Debug, RustcEncodable, RustcDecodable)]
+struct Game {
+    map: Map,
+    log: Messages,
+    inventory: Vec<Object>,
+    dungeon_level: u32,
+    item_knowledge: Vec<ItemKnowledge>,
+    gold: i32,
+    class: Class,
+    god_mode: bool,
+    // Tiles changed since the field of view was last updated; see `terrain`.
+    changed_tiles: Vec<(i32, i32)>,
+}
+
+fn new_game(name: &str, class: Class, ui: &mut Ui) -> (Vec<Object>, Game) {
+    let mut player = Object::new(0, 0, '@', name, colors::WHITE, true);
+    player.alive = true;
+    player.fighter = Some(class.fighter());
+    player.caster = Some(class.caster());
+    player.character = Some(class.character());
+    player.light = Some(Light { radius: TORCH_RADIUS, lit: true, fuel: None });
+    let max_hp_bonus = player.character.as_ref().map_or(0, |c| c.max_hp_bonus());
+    player.fighter.as_mut().unwrap().hp += max_hp_bonus;
+    let max_mana = player.max_mana();
+    player.caster.as_mut().unwrap().mana = max_mana;
+    let mut objects = vec![ player ];
+    let mut game = Game {
+        map: make_map(1, &mut objects),
+        log: vec![],
+        inventory: vec![],
+        dungeon_level: 1,
+        item_knowledge: new_item_knowledge(),
+        gold: class.starting_gold(),
+        class,
+        god_mode: false,
+        changed_tiles: vec![],
+    };
+
+    // Adventurers know what they packed.
+    for item in class.starting_kit() {
+        if let Some(knowledge) = game.item_knowledge.iter_mut().find(|k| Some(k.item) == item.item) {
+            knowledge.identified = true;
+        }
+        game.inventory.push(item);
+    }
+
+    initialize_fov(&game.map, ui);
+
+    game.log.add("Welcome stranger! Prepare to perish in the Tombs of the Ancient Kings.", colors::RED);
+
+    (objects, game)
+}
+
+fn initialize_fov(map: &Map, ui: &mut Ui) {
+    ui.fov.reset(map);
+    ui.view.invalidate();
+}
+
+fn play_game(objects: &mut Vec<Object>, game: &mut Game, ui: &mut Ui) {
+    let mut previous_player_position = (-1, -1);
+    let mut needs_redraw = true;
+    let mut turn_passed = true;
+    while !ui.renderer.window_closed() {
+        let event = ui.renderer.check_for_event();
+        let key = match event {
+            Some(Event::Mouse(m)) => {
+                ui.mouse = m;
+                Default::default()
+            },
+            Some(Event::Key(k)) => k,
+            _ => Default::default(),
+        };
+
+        // Lights move and burn out as turns pass, even when the player stays put.
+        if turn_passed || previous_player_position != objects[PLAYER].pos() {
+            update_fov(ui, objects, game);
+        }
+
+        // Nothing on screen changes between events, so quiet frames skip drawing.
+        if needs_redraw || event.is_some() {
+            render_all(ui, objects, game);
+            ui.renderer.flush();
+        } else {
+            ui.renderer.idle();
+        }
+        needs_redraw = event.is_some();
+        level_up(objects, game, ui);
+
+        previous_player_position = objects[PLAYER].pos();
+        let player_action = if ui.travel.is_some() {
+            // Any key stops travelling, and does nothing else.
+            if let Some(Event::Key(_)) = event {
+                ui.travel = None;
+                PlayerAction::DidntTakeTurn
+            } else {
+                needs_redraw = true;
+                travel::step(ui, objects, game)
+            }
+        } else {
+            // Clicking on the map travels there.
+            if let Some(Event::Mouse(mouse)) = event {
+                if mouse.lbutton_pressed && objects[PLAYER].alive {
+                    if let Some((x, y)) = ui.view.to_world(mouse.cx as i32, mouse.cy as i32) {
+                        travel::start(Goal::Tile(x, y), ui, objects, game);
+                    }
+                }
+            }
+            handle_keys(key, ui, game, objects)
+        };
+        if player_action == PlayerAction::Exit {
+            savefile::save(savefile::DEFAULT_PATH, objects, game).unwrap();
+            break
+        }
+
+        turn_passed = objects[PLAYER].alive && player_action != PlayerAction::DidntTakeTurn;
+        if turn_passed {
+            world_turn(objects, game, ui);
+        }
+    }
+}
+
+// Everything that happens after each turn the player takes: mana comes back,
+// lights burn down, monsters act, fire and gas spread and the dead drop what
+// they carried. Returns what killed the player, if anything did.
+fn world_turn(objects: &mut Vec<Object>, game: &mut Game, ui: &mut Ui) -> Option<String> {
+    let mut killer = None;
+    objects[PLAYER].recover_mana();
+    lighting::burn_fuel(objects, game);
+    let noise = Noise::spread(&game.map, objects[PLAYER].pos(), ui.noise);
+    ui.noise = 0;
+    for id in 0..objects.len() {
+        if objects[id].ai.is_some() {
+            ai_take_turn(id, game, objects, &ui.fov, &noise);
+            if !objects[PLAYER].alive && killer.is_none() {
+                killer = Some(objects[id].name.clone());
+            }
+        }
+    }
+    let (x, y) = objects[PLAYER].pos();
+    let hazard = game.map[x as usize][y as usize].effect;
+    effects::update(objects, game, &ui.fov);
+    if let Some(effect) = hazard.filter(|_| !objects[PLAYER].alive && killer.is_none()) {
+        killer = Some(effect.name().into());
+    }
+    drop_belongings(objects);
+    killer
+}
+
+fn msgbox(text: &str, width: i32, renderer: &mut dyn Renderer) {
+    let options: &[&str] = &[];
+    menu(text, options, width, renderer);
+}
+
+fn create_character(ui: &mut Ui) -> Option<(String, Class)> {
+    let name = loop {
+        match text_input("What is your name, adventurer?", CHARACTER_SCREEN_WIDTH, &mut *ui.renderer) {
+            Some(name) => {
+                if !name.trim().is_empty() {
+                    break name.trim().to_string();
+                }
+            },
+            None => return None,
+        }
+    };
+
+    let options: Vec<&str> = CLASSES.iter().map(|class| class.description()).collect();
+    let choice = menu(&format!("Choose a class for {}:\n", name), &options, Layout::of(&*ui.renderer).inventory_width, &mut *ui.renderer);
+    choice.map(|choice| (name, CLASSES[choice]))
+}
+
+fn main_menu(ui: &mut Ui) {
+    while !ui.renderer.window_closed() {
+        let layout = Layout::of(&*ui.renderer);
+        ui.renderer.clear();
+        ui.renderer.draw_menu_background();
+        print_centered(&mut *ui.renderer, layout.screen_width / 2, layout.screen_height / 2 - 4, "TOMB OF THE ANCIENT KINGS", colors::LIGHT_YELLOW);
+        print_centered(&mut *ui.renderer, layout.screen_width / 2, layout.screen_height / 2 - 2, "By Me", colors::LIGHT_YELLOW);
+        let choices = &["Play a new game", "Continue last game", "Quit"];
+        let choice = menu("", choices, 24, &mut *ui.renderer);
+        match choice {
+            Some(0) => {
+                if let Some((name, class)) = create_character(ui) {
+                    let (mut objects, mut game) = new_game(&name, class, ui);
+                    play_game(&mut objects, &mut game, ui);
+                }
+            },
+            Some(1) => {
+                match savefile::load(savefile::DEFAULT_PATH) {
+                    Ok((mut objects, mut game)) => {
+                        initialize_fov(&game.map, ui);
+                        play_game(&mut objects, &mut game, ui);
+                    },
+                    Err(_e) => {
+                        msgbox("\nNo saved game to load.\n", 24, &mut *ui.renderer);
+                        continue;
+                    }
+                }
+            },
+            Some(2) => {
+                break;
+            },
+            _ => {}
+        }
+    }
+}
+
+pub fn run() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.iter().any(|arg| arg == "--simulate") {
+        simulation::run(&args);
+        return;
+    }
+
+    let settings = Settings::load(SETTINGS_PATH).unwrap_or_else(|error| {
+        eprintln!("Ignoring {}: {}", SETTINGS_PATH, error);
+        Settings::default()
+    });
+
+    let terminal = args.iter().any(|arg| arg == "--terminal");
+    let renderer: Box<dyn Renderer> = if terminal {
+        terminal_renderer(&settings)
+    } else {
+        Box::new(TcodRenderer::new(&settings))
+    };
+
+    // The wizard console is for development, so release builds only get it
+    // when asked for.
+    let wizard = cfg!(debug_assertions) || args.iter().any(|arg| arg == "--wizard");
+    let mut ui = Ui {
+        renderer,
+        fov: Vision::new(settings.fov_algorithm),
+        mouse: Default::default(),
+        console: if wizard { Some(Console::load()) } else { None },
+        view: MapView::new(&settings),
+        travel: None,
+        noise: 0,
+    };
+
+    main_menu(&mut ui);
+}
+
+#[cfg(unix)]
+fn terminal_renderer(settings: &Settings) -> Box<dyn Renderer> {
+    Box::new(ansi::AnsiRenderer::new(settings.width, settings.height, settings.fps))
+}
+
+#[cfg(not(unix))]
+fn terminal_renderer(_settings: &Settings) -> Box<dyn Renderer> {
+    panic!("The terminal renderer is only available on unix systems.");
+}
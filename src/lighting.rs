@@ -0,0 +1,246 @@
+// Light and sight. Every light source casts light over the tiles it can reach,
+// fading with distance, and lit rooms glow all over. The player sees what is in
+// line of sight and has some light on it, or is close enough to touch. The same
+// light decides how far off monsters notice the player, so putting out your
+// light is a way to creep past them.
+
+use tcod::map::{ Map as FovMap, FovAlgorithm };
+
+use super::*;
+
+// No one sees further than this, however bright things are.
+const SIGHT_RADIUS: i32 = 20;
+// How far monsters notice a fully lit player from. Dimmer light brings them
+// closer, down to being next to the player.
+const NOTICE_RADIUS: f32 = 10.0;
+const LIT_ROOM_LEVEL: f32 = 0.6;
+
+pub const LANTERN_RADIUS: i32 = 15;
+pub const LANTERN_FUEL: i32 = 800;
+
+#[derive(Clone, Copy, Debug, PartialEq, RustcEncodable, RustcDecodable)]
+pub struct Light {
+    pub radius: i32,
+    pub lit: bool,
+    // Turns left for lights that burn out, like lanterns.
+    pub fuel: Option<i32>,
+}
+
+impl Light {
+    pub fn burning(&self) -> bool {
+        self.lit && self.fuel != Some(0)
+    }
+}
+
+pub struct Vision {
+    // The shape of the level, shared by the player's sight and every light.
+    fov: FovMap,
+    algorithm: FovAlgorithm,
+    width: i32,
+    height: i32,
+    light: Vec<f32>,
+    in_sight: Vec<bool>,
+    visible: Vec<(i32, i32)>,
+    player: (i32, i32),
+}
+
+impl Vision {
+    pub fn new(algorithm: FovAlgorithm) -> Vision {
+        Vision {
+            fov: FovMap::new(1, 1),
+            algorithm,
+            width: 1,
+            height: 1,
+            light: vec![0.0],
+            in_sight: vec![false],
+            visible: vec![],
+            player: (0, 0),
+        }
+    }
+
+    // For a new level.
+    pub fn reset(&mut self, map: &Map) {
+        self.width = map_width(map);
+        self.height = map_height(map);
+        self.fov = FovMap::new(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                self.set(x, y, &map[x as usize][y as usize]);
+            }
+        }
+        let size = (self.width * self.height) as usize;
+        self.light = vec![0.0; size];
+        self.in_sight = vec![false; size];
+        self.visible = vec![];
+    }
+
+    // For a tile that has changed.
+    pub fn set(&mut self, x: i32, y: i32, tile: &Tile) {
+        self.fov.set(x, y, !tile.block_sight, !tile.blocked);
+    }
+
+    pub fn update(&mut self, objects: &[Object], game: &Game) {
+        for level in &mut self.light {
+            *level = 0.0;
+        }
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let tile = &game.map[x as usize][y as usize];
+                if matches!(tile.effect, Some(Effect::Fire { .. })) {
+                    self.light[(y * self.width + x) as usize] = 1.0;
+                } else if tile.lit {
+                    self.light[(y * self.width + x) as usize] = LIT_ROOM_LEVEL;
+                }
+            }
+        }
+        for (position, radius) in light_sources(objects, game) {
+            self.cast_light(position, radius);
+        }
+
+        // The player's sight goes last: the FOV map only keeps the latest one.
+        let player = objects[PLAYER].pos();
+        self.player = player;
+        self.fov.compute_fov(player.0, player.1, SIGHT_RADIUS, FOV_LIGHT_WALLS, self.algorithm);
+        self.visible.clear();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = (y * self.width + x) as usize;
+                let near = (x - player.0).abs() <= SIGHT_RADIUS && (y - player.1).abs() <= SIGHT_RADIUS;
+                self.in_sight[index] = near && self.fov.is_in_fov(x, y);
+                let touching = (x - player.0).abs() <= 1 && (y - player.1).abs() <= 1;
+                if self.in_sight[index] && (self.light[index] > 0.0 || touching) {
+                    self.visible.push((x, y));
+                }
+            }
+        }
+    }
+
+    fn cast_light(&mut self, (source_x, source_y): (i32, i32), radius: i32) {
+        self.fov.compute_fov(source_x, source_y, radius, FOV_LIGHT_WALLS, self.algorithm);
+        for y in cmp::max(0, source_y - radius)..cmp::min(self.height, source_y + radius + 1) {
+            for x in cmp::max(0, source_x - radius)..cmp::min(self.width, source_x + radius + 1) {
+                let distance = (((x - source_x).pow(2) + (y - source_y).pow(2)) as f32).sqrt();
+                if distance <= radius as f32 && self.fov.is_in_fov(x, y) {
+                    let level = &mut self.light[(y * self.width + x) as usize];
+                    *level = (*level + 1.0 - distance / (radius + 1) as f32).min(1.0);
+                }
+            }
+        }
+    }
+
+    pub fn is_in_fov(&self, x: i32, y: i32) -> bool {
+        self.in_sight(x, y) && (self.light(x, y) > 0.0 || ((x - self.player.0).abs() <= 1 && (y - self.player.1).abs() <= 1))
+    }
+
+    // In line of sight of the player, lit or not.
+    pub fn in_sight(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.width && y < self.height && self.in_sight[(y * self.width + x) as usize]
+    }
+
+    // From 0 for darkness to 1 for full light.
+    pub fn light(&self, x: i32, y: i32) -> f32 {
+        if x >= 0 && y >= 0 && x < self.width && y < self.height {
+            self.light[(y * self.width + x) as usize]
+        } else {
+            0.0
+        }
+    }
+
+    pub fn visible_tiles(&self) -> &[(i32, i32)] {
+        &self.visible
+    }
+
+    // Whether a monster at (x, y) notices the player: it has to be in line of
+    // sight and close enough for the light the player stands in.
+    pub fn notices_player(&self, x: i32, y: i32) -> bool {
+        let distance = (((x - self.player.0).pow(2) + (y - self.player.1).pow(2)) as f32).sqrt();
+        let light = self.light(self.player.0, self.player.1);
+        self.in_sight(x, y) && distance <= (NOTICE_RADIUS * light).max(1.5)
+    }
+}
+
+// Where each burning light is and how far it reaches. Items lying on the floor
+// don't count; a dropped lantern goes out.
+fn light_sources(objects: &[Object], game: &Game) -> Vec<((i32, i32), i32)> {
+    let mut sources = vec![];
+    let radius = player_light_radius(&objects[PLAYER], game);
+    if radius > 0 {
+        sources.push((objects[PLAYER].pos(), radius));
+    }
+    for object in objects.iter().skip(1).filter(|object| object.item.is_none()) {
+        if let Some(light) = object.light.filter(Light::burning) {
+            sources.push((object.pos(), light.radius));
+        }
+    }
+    sources
+}
+
+// The player's own light, or a lantern with fuel left if it reaches further.
+pub fn player_light_radius(player: &Object, game: &Game) -> i32 {
+    match player.light {
+        Some(light) if light.lit => {
+            let lantern = equipped_lanterns(game).filter_map(|lantern| lantern.light).filter(Light::burning).map(|light| light.radius).max();
+            cmp::max(light.radius, lantern.unwrap_or(0))
+        },
+        _ => 0,
+    }
+}
+
+fn equipped_lanterns(game: &Game) -> impl Iterator<Item = &Object> {
+    game.inventory.iter().filter(|item| item.light.is_some() && item.equipment.map_or(false, |e| e.equipped))
+}
+
+// Equipped lanterns burn fuel each turn the player's light is lit.
+pub fn burn_fuel(objects: &[Object], game: &mut Game) {
+    if !objects[PLAYER].light.map_or(false, |light| light.lit) {
+        return;
+    }
+    let mut burnt_out = false;
+    for lantern in game.inventory.iter_mut().filter(|item| item.equipment.map_or(false, |e| e.equipped)) {
+        if let Some(Light { fuel: Some(ref mut fuel), .. }) = lantern.light {
+            if *fuel > 0 {
+                *fuel -= 1;
+                burnt_out |= *fuel == 0;
+            }
+        }
+    }
+    if burnt_out {
+        game.log.add("Your lantern sputters and goes out.", colors::ORANGE);
+    }
+}
+
+// Putting the player's light out hides them in the dark, unless they stand in
+// someone else's light.
+pub fn toggle_light(objects: &mut [Object], game: &mut Game) {
+    if let Some(ref mut light) = objects[PLAYER].light {
+        light.lit = !light.lit;
+        if light.lit {
+            game.log.add("You light your torch.", colors::LIGHT_YELLOW);
+        } else {
+            game.log.add("You put out your light. The darkness hides you.", colors::LIGHT_GREY);
+        }
+    }
+}
+
+// Rooms are sometimes lit all over, more often near the surface. Dark rooms
+// may get a torch on the wall instead.
+pub fn light_room(level: u32, room: Rect, map: &mut Map, objects: &mut Vec<Object>) {
+    let lit_chance = from_dungeon_level(&[
+        Transition { level: 1, value: 50 },
+        Transition { level: 3, value: 30 },
+        Transition { level: 6, value: 15 },
+    ], level);
+    if rng().gen_range(0, 100) < lit_chance {
+        for x in room.x1..room.x2 + 1 {
+            for y in room.y1..room.y2 + 1 {
+                map[x as usize][y as usize].lit = true;
+            }
+        }
+    } else if rng().gen_range(0, 3) == 0 {
+        let (x, _) = room.center();
+        let mut torch = Object::new(x, room.y1 + 1, '*', "wall torch", colors::LIGHTER_FLAME, false);
+        torch.always_visible = true;
+        torch.light = Some(Light { radius: 6, lit: true, fuel: None });
+        objects.push(torch);
+    }
+}
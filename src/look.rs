@@ -0,0 +1,255 @@
+// The look command: a cursor to move over the map, with a panel describing
+// whatever is under it. Tiles out of sight are described as the player last
+// saw them, which leaves out anything that may have moved.
+
+use tcod::input::KeyCode;
+
+use descriptions::describe;
+use targeting::{ Cursor, tint };
+
+use super::*;
+
+const PANEL_WIDTH: i32 = 32;
+
+pub fn look(ui: &mut Ui, objects: &[Object], game: &mut Game) {
+    let mut cursor = Cursor::new(objects, &ui.fov, false);
+    let mut redraw = true;
+    loop {
+        if redraw {
+            draw(ui, objects, game, cursor.position);
+            ui.renderer.flush();
+        }
+        redraw = true;
+
+        match ui.renderer.check_for_event() {
+            Some(Event::Mouse(mouse)) => {
+                ui.mouse = mouse;
+                cursor.handle_mouse(mouse, &ui.view);
+                if mouse.rbutton_pressed {
+                    return;
+                }
+            },
+            Some(Event::Key(key)) if key.code == KeyCode::Escape || key.printable == 'l' => return,
+            Some(Event::Key(key)) => redraw = cursor.handle_key(key, objects, &game.map),
+            None => {
+                ui.renderer.idle();
+                redraw = false;
+            },
+        }
+    }
+}
+
+// The lines of the description panel for a tile.
+pub fn describe_tile((x, y): (i32, i32), objects: &[Object], game: &Game, fov: &Vision) -> Vec<(String, Color)> {
+    let tile = &game.map[x as usize][y as usize];
+    if !tile.explored {
+        return vec![("You haven't seen this place.".to_string(), colors::LIGHT_GREY)];
+    }
+
+    let visible = fov.is_in_fov(x, y);
+    let mut lines = vec![];
+    lines.push((if visible { "You see:" } else { "You remember:" }.to_string(), colors::LIGHT_GREY));
+
+    let here = objects.iter().filter(|object| object.pos() == (x, y) && (visible || object.always_visible || object.seen));
+    for object in here {
+        lines.push((String::new(), colors::WHITE));
+        if object.is_player() {
+            lines.push(("Yourself".to_string(), object.color));
+            lines.extend(fighter_lines(object, game));
+            continue;
+        }
+        lines.push((capitalize(&item_name(object, game)), object.color));
+        if let Some(description) = describe(&known_name(object, game)) {
+            lines.push((description.to_string(), colors::LIGHT_GREY));
+        }
+        if object.fighter.is_some() {
+            lines.extend(fighter_lines(object, game));
+        }
+        if let Some(equipment) = enchantment::known_equipment(object) {
+            lines.push((equipment_line(equipment), colors::WHITE));
+        }
+        lines.extend(enchantment_lines(object));
+        if let Some(durability) = object.durability {
+            let line = format!("Condition: {} ({}/{})", durability.condition(), durability.current, durability.max);
+            lines.push((line, if durability.is_damaged() { colors::ORANGE } else { colors::WHITE }));
+        }
+        if let Some(line) = light_line(object) {
+            lines.push((line, colors::LIGHT_YELLOW));
+        }
+        if let Some(line) = container_line(object) {
+            lines.push((line, colors::WHITE));
+        }
+    }
+
+    let terrain = match (tile.door, tile.blocked) {
+        (true, true) => "closed door",
+        (true, false) => "open door",
+        (false, true) if tile.cracked => "cracked wall",
+        (false, true) if terrain::is_partly_dug(tile) => "partly dug wall",
+        (false, true) => "wall",
+        (false, false) => "floor",
+    };
+    lines.push((String::new(), colors::WHITE));
+    lines.push((capitalize(terrain), colors::WHITE));
+    if let Some(description) = describe(terrain) {
+        lines.push((description.to_string(), colors::LIGHT_GREY));
+    }
+    if tile.flammable {
+        lines.push((capitalize("dry brush"), colors::WHITE));
+        if let Some(description) = describe("dry brush") {
+            lines.push((description.to_string(), colors::LIGHT_GREY));
+        }
+    }
+    if let Some(effect) = tile.effect.filter(|_| visible) {
+        lines.push((capitalize(&effect_line(effect)), colors::WHITE));
+        if let Some(description) = describe(effect.name()) {
+            lines.push((description.to_string(), colors::LIGHT_GREY));
+        }
+    }
+    if visible {
+        lines.push((light_level(fov.light(x, y)).to_string(), colors::LIGHT_GREY));
+    }
+    lines
+}
+
+fn effect_line(effect: Effect) -> String {
+    match effect {
+        Effect::Fire { turns } => format!("fire, burning for {} more turns", turns),
+        Effect::Gas { density } if density > 3 => "thick poison gas".to_string(),
+        Effect::Gas { .. } => "thin poison gas".to_string(),
+        Effect::Water { depth } if depth > 1 => "deep water".to_string(),
+        Effect::Water { .. } => "shallow water".to_string(),
+    }
+}
+
+fn light_line(object: &Object) -> Option<String> {
+    let light = object.light?;
+    Some(match light.fuel {
+        Some(0) => "Out of fuel".to_string(),
+        Some(fuel) => format!("Fuel for {} more turns", fuel),
+        None if object.fighter.is_some() && !object.is_player() => "Carries a torch".to_string(),
+        None => return None,
+    })
+}
+
+// Whether a chest is locked is plain to see, but not what it holds until it is
+// opened. A bag can be looked into.
+fn container_line(object: &Object) -> Option<String> {
+    let container = object.container?;
+    Some(match object.item {
+        Some(_) => format!("Holds {} of {} items", object.inventory.len(), container.capacity),
+        None if container.locked => "Locked".to_string(),
+        None => return None,
+    })
+}
+
+fn light_level(light: f32) -> &'static str {
+    if light >= 0.75 {
+        "Brightly lit"
+    } else if light >= 0.4 {
+        "Lit"
+    } else if light > 0.0 {
+        "Dimly lit"
+    } else {
+        "Dark"
+    }
+}
+
+// Unidentified items are described by what they look like.
+fn known_name(object: &Object, game: &Game) -> String {
+    match object.item.and_then(|item| item_knowledge(item, game)) {
+        Some(knowledge) if !knowledge.identified => knowledge.appearance.clone(),
+        _ => object.name.clone(),
+    }
+}
+
+fn fighter_lines(object: &Object, game: &Game) -> Vec<(String, Color)> {
+    let hp = object.fighter.map_or(0, |f| f.hp);
+    let max_hp = cmp::max(1, object.max_hp(game));
+    let (health, color) = match hp * 4 / max_hp {
+        4 => ("Unhurt", colors::LIGHT_GREEN),
+        3 => ("Lightly wounded", colors::LIGHT_YELLOW),
+        2 => ("Wounded", colors::ORANGE),
+        1 => ("Badly wounded", colors::LIGHT_RED),
+        _ => ("Almost dead", colors::RED),
+    };
+    let mut lines = vec![
+        (format!("{} ({}/{} HP)", health, hp, max_hp), color),
+        (format!("Attack {}, defense {}", object.power(game), object.defense(game)), colors::WHITE),
+    ];
+    match object.ai {
+        Some(Ai::Confused { num_turns, .. }) => lines.push((format!("Confused for {} more turns", num_turns), colors::LIGHT_GREEN)),
+        Some(Ai::Shopkeeper) => lines.push(("Peaceful".to_string(), colors::LIGHT_BLUE)),
+        Some(Ai::Basic) => lines.push(("Hostile".to_string(), colors::LIGHT_RED)),
+        Some(Ai::Unaware) => lines.push(("Hostile, but unaware of you".to_string(), colors::ORANGE)),
+        None => {},
+    }
+    lines
+}
+
+// What is known of an item's magic.
+fn enchantment_lines(object: &Object) -> Vec<(String, Color)> {
+    let enchantment = match object.enchantment {
+        Some(enchantment) if enchantment.identified => enchantment,
+        _ => return vec![],
+    };
+    let mut lines = vec![];
+    for affix in enchantment.prefix.iter().chain(&enchantment.suffix) {
+        lines.push((format!("{}: {}", capitalize(affix.name()), affix.description()), colors::LIGHT_BLUE));
+    }
+    if enchantment.cursed {
+        lines.push(("Cursed: it can't be taken off once worn".to_string(), colors::RED));
+    }
+    lines
+}
+
+fn equipment_line(equipment: Equipment) -> String {
+    let mut bonuses = vec![];
+    if equipment.power_bonus != 0 {
+        bonuses.push(format!("{:+} attack", equipment.power_bonus));
+    }
+    if equipment.defense_bonus != 0 {
+        bonuses.push(format!("{:+} defense", equipment.defense_bonus));
+    }
+    if equipment.max_hp_bonus != 0 {
+        bonuses.push(format!("{:+} max HP", equipment.max_hp_bonus));
+    }
+    if bonuses.is_empty() {
+        format!("Worn on the {}", equipment.slot)
+    } else {
+        format!("Worn on the {}: {}", equipment.slot, bonuses.join(", "))
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn draw(ui: &mut Ui, objects: &[Object], game: &Game, cursor: (i32, i32)) {
+    render_all(ui, objects, game);
+    tint(ui, cursor, colors::LIGHT_YELLOW, 0.6);
+
+    // The panel goes on whichever side of the map the cursor isn't.
+    let layout = Layout::of(&*ui.renderer);
+    let on_left = matches!(ui.view.to_screen(cursor.0, cursor.1), Some((screen_x, _)) if screen_x >= layout.view_width / 2);
+    let x = if on_left { 0 } else { layout.view_width - PANEL_WIDTH };
+
+    let mut lines = vec![];
+    for (text, color) in describe_tile(cursor, objects, game, &ui.fov) {
+        for line in wrap_text(&text, PANEL_WIDTH - 2) {
+            lines.push((line, color));
+        }
+    }
+    let height = cmp::min(lines.len() as i32 + 2, layout.view_height);
+    clear_rect(&mut *ui.renderer, x, 0, PANEL_WIDTH, height);
+    for (index, &(ref line, color)) in lines.iter().take((height - 2) as usize).enumerate() {
+        ui.renderer.print(x + 1, 1 + index as i32, line, color);
+    }
+
+    clear_rect(&mut *ui.renderer, 0, layout.panel_y - 1, layout.screen_width, 1);
+    ui.renderer.print(0, layout.panel_y - 1, "Look: movement keys move, Tab cycles monsters, Escape stops.", colors::LIGHT_YELLOW);
+}
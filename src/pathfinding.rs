@@ -0,0 +1,62 @@
+// Paths over the explored part of a map, for travelling and for the simulation
+// bot. Only explored tiles are considered, so neither ever knows more about the
+// level than the player does.
+
+use std::collections::VecDeque;
+
+use super::*;
+
+// Straight steps come before diagonal ones, so that of all the shortest paths
+// the search prefers the one that looks most direct.
+const DIRECTIONS: &[(i32, i32)] = &[(0, -1), (0, 1), (-1, 0), (1, 0), (-1, -1), (1, -1), (-1, 1), (1, 1)];
+
+pub fn neighbours(x: i32, y: i32, map: &Map) -> Vec<(i32, i32)> {
+    DIRECTIONS.iter()
+        .map(|&(dx, dy)| (x + dx, y + dy))
+        .filter(|&(nx, ny)| in_map(nx, ny, map))
+        .collect()
+}
+
+// Whether exploring from here could find out more: some open neighbour hasn't
+// been seen yet.
+pub fn borders_unexplored(x: i32, y: i32, map: &Map) -> bool {
+    neighbours(x, y, map).iter().any(|&(nx, ny)| {
+        let tile = &map[nx as usize][ny as usize];
+        !tile.explored && !tile.blocked
+    })
+}
+
+// Breadth-first search from `start` over explored floor, avoiding tiles that
+// are `obstacle`, to the nearest tile matching `goal`. Returns the steps to
+// get there, not including `start`.
+pub fn find_path<O, G>(map: &Map, start: (i32, i32), obstacle: O, goal: G) -> Option<Vec<(i32, i32)>>
+        where O: Fn(i32, i32) -> bool, G: Fn(i32, i32) -> bool {
+    let width = map_width(map);
+    let index = |(x, y): (i32, i32)| (y * width + x) as usize;
+    let mut came_from = vec![None; (width * map_height(map)) as usize];
+
+    let mut queue = VecDeque::new();
+    came_from[index(start)] = Some(start);
+    queue.push_back(start);
+
+    while let Some((x, y)) = queue.pop_front() {
+        if (x, y) != start && goal(x, y) {
+            let mut path = vec![(x, y)];
+            while let Some(previous) = came_from[index(path[path.len() - 1])].filter(|&previous| previous != start) {
+                path.push(previous);
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for (nx, ny) in neighbours(x, y, map) {
+            let tile = &map[nx as usize][ny as usize];
+            // Closed doors are in the way only until opened.
+            if (tile.blocked && !tile.door) || !tile.explored || came_from[index((nx, ny))].is_some() || obstacle(nx, ny) {
+                continue;
+            }
+            came_from[index((nx, ny))] = Some((x, y));
+            queue.push_back((nx, ny));
+        }
+    }
+    None
+}
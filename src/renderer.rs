@@ -0,0 +1,240 @@
+use std::cell::RefCell;
+use std::cmp;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+
+use tcod::console::*;
+use tcod::colors::{ self, Color };
+use tcod::input::{ self, Key, KeyCode, Event };
+use tcod::image::{ self, Image };
+use tcod_sys;
+
+use settings::{ Settings, DEFAULT_WIDTH, DEFAULT_HEIGHT };
+
+// Everything the game needs from a display: a grid of colored characters and a
+// stream of key and mouse events.
+pub trait Renderer {
+    fn width(&self) -> i32;
+    fn height(&self) -> i32;
+    fn clear(&mut self);
+    fn put_char(&mut self, x: i32, y: i32, c: char, color: Color);
+    fn set_background(&mut self, x: i32, y: i32, color: Color);
+    fn print(&mut self, x: i32, y: i32, text: &str, color: Color);
+    fn flush(&mut self);
+    fn check_for_event(&mut self) -> Option<Event>;
+    fn wait_for_keypress(&mut self) -> Key;
+    fn window_closed(&self) -> bool;
+
+    // Called instead of `flush` on frames where nothing changed, so the frame
+    // loop still runs at a steady rate without drawing.
+    fn idle(&mut self) {}
+    fn toggle_fullscreen(&mut self) {}
+    fn draw_menu_background(&mut self) {}
+}
+
+// One character cell of the screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cell {
+    pub c: char,
+    pub fg: Color,
+    pub bg: Color,
+}
+
+pub const BLANK: Cell = Cell { c: ' ', fg: colors::WHITE, bg: colors::BLACK };
+
+// Splits text into lines no wider than `width`, breaking on spaces where possible.
+pub fn wrap_text(text: &str, width: i32) -> Vec<String> {
+    let width = cmp::max(width, 1) as usize;
+    let mut lines = vec![];
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            let mut word = word.to_string();
+            while word.chars().count() > width {
+                if !line.is_empty() {
+                    lines.push(line);
+                    line = String::new();
+                }
+                let rest = word.split_off(word.char_indices().nth(width).map_or(word.len(), |(i, _)| i));
+                lines.push(word);
+                word = rest;
+            }
+            if line.is_empty() {
+                line = word;
+            } else if line.chars().count() + 1 + word.chars().count() <= width {
+                line.push(' ');
+                line.push_str(&word);
+            } else {
+                lines.push(line);
+                line = word;
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+pub fn print_centered(renderer: &mut dyn Renderer, x: i32, y: i32, text: &str, color: Color) {
+    let len = text.chars().count() as i32;
+    renderer.print(x - len / 2, y, text, color);
+}
+
+pub fn clear_rect(renderer: &mut dyn Renderer, x: i32, y: i32, width: i32, height: i32) {
+    for cy in y..y + height {
+        for cx in x..x + width {
+            renderer.put_char(cx, cy, ' ', colors::WHITE);
+            renderer.set_background(cx, cy, colors::BLACK);
+        }
+    }
+}
+
+pub struct TcodRenderer {
+    root: Root,
+    menu_background: Option<Image>,
+    frame: Duration,
+}
+
+impl TcodRenderer {
+    pub fn new(settings: &Settings) -> TcodRenderer {
+        let mut initializer = Root::initializer();
+        initializer
+                .font(&settings.font, settings.font_layout)
+                .font_type(if settings.greyscale { FontType::Greyscale } else { FontType::Default })
+                .size(settings.width, settings.height)
+                .title(&settings.title)
+                .fullscreen(settings.fullscreen);
+        if let Some((columns, rows)) = settings.font_size {
+            initializer.font_dimensions(columns, rows);
+        }
+        let root = initializer.init();
+
+        for &(glyph, column, row) in &settings.tiles {
+            unsafe {
+                tcod_sys::TCOD_console_map_ascii_code_to_font(glyph as i32, column, row);
+            }
+        }
+
+        ::tcod::system::set_fps(settings.fps);
+
+        TcodRenderer {
+            root,
+            menu_background: Image::from_file("menu_background.png").ok(),
+            frame: Duration::from_millis(1000 / settings.fps as u64),
+        }
+    }
+
+    fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < self.root.width() && y < self.root.height()
+    }
+}
+
+impl Renderer for TcodRenderer {
+    fn width(&self) -> i32 {
+        self.root.width()
+    }
+
+    fn height(&self) -> i32 {
+        self.root.height()
+    }
+
+    fn clear(&mut self) {
+        self.root.set_default_background(colors::BLACK);
+        self.root.clear();
+    }
+
+    fn put_char(&mut self, x: i32, y: i32, c: char, color: Color) {
+        if self.in_bounds(x, y) {
+            self.root.set_default_foreground(color);
+            self.root.put_char(x, y, c, BackgroundFlag::None);
+        }
+    }
+
+    fn set_background(&mut self, x: i32, y: i32, color: Color) {
+        if self.in_bounds(x, y) {
+            self.root.set_char_background(x, y, color, BackgroundFlag::Set);
+        }
+    }
+
+    fn print(&mut self, x: i32, y: i32, text: &str, color: Color) {
+        self.root.set_default_foreground(color);
+        self.root.print_ex(x, y, BackgroundFlag::None, TextAlignment::Left, text);
+    }
+
+    fn flush(&mut self) {
+        self.root.flush();
+    }
+
+    fn check_for_event(&mut self) -> Option<Event> {
+        input::check_for_event(input::MOUSE | input::KEY_PRESS).map(|e| e.1)
+    }
+
+    fn wait_for_keypress(&mut self) -> Key {
+        self.root.wait_for_keypress(true)
+    }
+
+    fn window_closed(&self) -> bool {
+        self.root.window_closed()
+    }
+
+    fn idle(&mut self) {
+        thread::sleep(self.frame);
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        let fullscreen = self.root.is_fullscreen();
+        self.root.set_fullscreen(!fullscreen);
+    }
+
+    fn draw_menu_background(&mut self) {
+        if let Some(ref img) = self.menu_background {
+            image::blit_2x(img, (0, 0), (-1, -1), &mut self.root, (0, 0));
+        }
+    }
+}
+
+// A renderer with no screen, for simulations and tests. Key presses come from a
+// shared queue that the caller fills in; once it runs dry every prompt gets
+// Escape, which cancels it.
+pub struct ScriptedRenderer {
+    keys: Rc<RefCell<VecDeque<Key>>>,
+}
+
+impl ScriptedRenderer {
+    pub fn new(keys: Rc<RefCell<VecDeque<Key>>>) -> ScriptedRenderer {
+        ScriptedRenderer { keys }
+    }
+
+    fn next_key(&mut self) -> Key {
+        self.keys.borrow_mut().pop_front().unwrap_or(Key { code: KeyCode::Escape, pressed: true, ..Default::default() })
+    }
+}
+
+impl Renderer for ScriptedRenderer {
+    fn width(&self) -> i32 {
+        DEFAULT_WIDTH
+    }
+
+    fn height(&self) -> i32 {
+        DEFAULT_HEIGHT
+    }
+
+    fn clear(&mut self) {}
+    fn put_char(&mut self, _x: i32, _y: i32, _c: char, _color: Color) {}
+    fn set_background(&mut self, _x: i32, _y: i32, _color: Color) {}
+    fn print(&mut self, _x: i32, _y: i32, _text: &str, _color: Color) {}
+    fn flush(&mut self) {}
+
+    fn check_for_event(&mut self) -> Option<Event> {
+        Some(Event::Key(self.next_key()))
+    }
+
+    fn wait_for_keypress(&mut self) -> Key {
+        self.next_key()
+    }
+
+    fn window_closed(&self) -> bool {
+        false
+    }
+}
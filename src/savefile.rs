@@ -0,0 +1,280 @@
+// Save files, and the `savetool` commands for inspecting and editing them.
+//
+// A save is the JSON encoding of `(version, objects, game)`, using the same
+// types the game plays with.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::{ Read, Write };
+
+use rustc_serialize::json::{ self, Json };
+
+use super::*;
+
+pub const SAVE_VERSION: u64 = 12;
+pub const DEFAULT_PATH: &str = "savegame";
+
+pub(crate) fn save(path: &str, objects: &[Object], game: &Game) -> Result<(), Box<dyn Error>> {
+    let save_data = json::encode(&(SAVE_VERSION, objects, game))?;
+    let mut file = File::create(path)?;
+    file.write_all(save_data.as_bytes())?;
+    Ok(())
+}
+
+pub(crate) fn load(path: &str) -> Result<(Vec<Object>, Game), Box<dyn Error>> {
+    let mut json_save_state = String::new();
+    let mut file = File::open(path)?;
+    file.read_to_string(&mut json_save_state)?;
+    check_version(&json_save_state)?;
+    let (_, objects, game) = json::decode::<(u64, Vec<Object>, Game)>(&json_save_state)?;
+    Ok((objects, game))
+}
+
+fn check_version(json_save_state: &str) -> Result<(), String> {
+    let data = Json::from_str(json_save_state).map_err(|e| format!("Not a save file: {}", e))?;
+    let version = match data {
+        Json::Array(ref parts) if parts.len() == 3 => parts[0].as_u64(),
+        Json::Array(ref parts) if parts.len() == 2 => return Err("Save file is from before save versions were recorded.".into()),
+        _ => None,
+    };
+    match version {
+        Some(SAVE_VERSION) => Ok(()),
+        Some(version) => Err(format!("Save file is version {}, but this game reads version {}.", version, SAVE_VERSION)),
+        None => Err("Not a save file: expected [version, objects, game].".into()),
+    }
+}
+
+const USAGE: &str = "usage: savetool [--file PATH] COMMAND
+
+commands:
+    summary          level, player stats, inventory and monster counts
+    map              the map as ASCII
+    validate         check the save can be loaded and is consistent
+    give ITEM        add an item to the inventory, by name
+    set-hp HP        set the player's hit points
+    teleport X Y     move the player
+    reveal           mark the whole map as explored";
+
+// Entry point for the `savetool` binary.
+pub fn run_tool(args: &[String]) -> Result<(), String> {
+    let mut path = DEFAULT_PATH.to_string();
+    let mut words = vec![];
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--file" {
+            path = args.next().ok_or(USAGE)?.clone();
+        } else {
+            words.push(arg.as_str());
+        }
+    }
+
+    let command = *words.first().ok_or(USAGE)?;
+    if command == "validate" {
+        return validate_file(&path);
+    }
+
+    let (mut objects, mut game) = load(&path).map_err(|e| format!("Could not load {}: {}", path, e))?;
+    match (command, &words[1..]) {
+        ("summary", &[]) => print!("{}", summary(&objects, &game)),
+        ("map", &[]) => print!("{}", map_dump(&objects, &game)),
+        ("give", names) if !names.is_empty() => give_item(&names.join(" "), &mut game)?,
+        ("set-hp", &[hp]) => set_hp(parse_number(hp)?, &mut objects, &game)?,
+        ("teleport", &[x, y]) => teleport_player(parse_number(x)?, parse_number(y)?, &mut objects, &game)?,
+        ("reveal", &[]) => reveal_map(&mut game),
+        _ => return Err(USAGE.into()),
+    }
+
+    if command != "summary" && command != "map" {
+        save(&path, &objects, &game).map_err(|e| format!("Could not save {}: {}", path, e))?;
+    }
+    Ok(())
+}
+
+fn parse_number(text: &str) -> Result<i32, String> {
+    text.parse().map_err(|_| format!("Not a number: {}", text))
+}
+
+fn summary(objects: &[Object], game: &Game) -> String {
+    let player = &objects[PLAYER];
+    let mut lines = vec![];
+    lines.push(format!("Dungeon level {}", game.dungeon_level));
+    lines.push(format!("{} the {}, level {}{}", player.name, game.class, player.level,
+                       if player.alive { "" } else { " (dead)" }));
+    if let Some(fighter) = player.fighter {
+        let level_up_xp = LEVEL_UP_BASE + player.level * LEVEL_UP_FACTOR;
+        lines.push(format!("HP {}/{}  Power {}  Defense {}  XP {}/{}", fighter.hp, player.max_hp(game),
+                           player.power(game), player.defense(game), fighter.xp, level_up_xp));
+    }
+    if let Some(ref caster) = player.caster {
+        lines.push(format!("Mana {}/{}", caster.mana, player.max_mana()));
+    }
+    lines.push(format!("Position ({}, {})  Gold {}", player.x, player.y, game.gold));
+
+    lines.push(format!("Inventory ({}/{}):", game.inventory.len(), INVENTORY_SIZE));
+    for (index, item) in game.inventory.iter().enumerate() {
+        let mut line = format!("  ({}) {}", (b'a' + index as u8) as char, enchantment::full_name(item));
+        if let Some(equipment) = item.equipment.filter(|equipment| equipment.equipped) {
+            line.push_str(&format!(" (on {})", equipment.slot));
+        }
+        if let Some(durability) = item.durability {
+            line.push_str(&format!(" ({}/{})", durability.current, durability.max));
+        }
+        if is_unidentified(item, game) {
+            line.push_str(&format!(" (unidentified, looks like a {})", item_name(item, game)));
+        }
+        lines.push(line);
+        for content in &item.inventory {
+            lines.push(format!("      {}", content.name));
+        }
+    }
+
+    let mut monsters = BTreeMap::new();
+    for object in objects.iter().skip(1).filter(|object| object.fighter.is_some()) {
+        *monsters.entry(object.name.as_str()).or_insert(0) += 1;
+    }
+    lines.push(format!("Monsters on this level ({}):", monsters.values().sum::<i32>()));
+    for (name, count) in monsters {
+        lines.push(format!("  {}: {}", name, count));
+    }
+
+    let floor_items = objects.iter().filter(|object| object.item.is_some()).count();
+    lines.push(format!("Items on the floor: {}", floor_items));
+    lines.join("\n") + "\n"
+}
+
+// The whole level, explored or not: walls, doors, floor, then objects with the
+// player on top.
+fn map_dump(objects: &[Object], game: &Game) -> String {
+    let glyph = |tile: &Tile| match (tile.door, tile.blocked) {
+        (true, true) => '+',
+        (true, false) => '\'',
+        (false, true) => '#',
+        (false, false) => '.',
+    };
+    let mut rows: Vec<Vec<char>> = (0..map_height(&game.map) as usize).map(|y| {
+        (0..map_width(&game.map) as usize).map(|x| glyph(&game.map[x][y])).collect()
+    }).collect();
+
+    let mut to_draw: Vec<&Object> = objects.iter().skip(1).collect();
+    to_draw.sort_by_key(|object| object.blocks);
+    to_draw.push(&objects[PLAYER]);
+    for object in to_draw {
+        if in_map(object.x, object.y, &game.map) {
+            rows[object.y as usize][object.x as usize] = object.char;
+        }
+    }
+
+    rows.into_iter().map(|row| row.into_iter().collect::<String>() + "\n").collect()
+}
+
+fn validate_file(path: &str) -> Result<(), String> {
+    let mut json_save_state = String::new();
+    File::open(path).and_then(|mut file| file.read_to_string(&mut json_save_state))
+        .map_err(|e| format!("Could not read {}: {}", path, e))?;
+    check_version(&json_save_state)?;
+    let (_, objects, game) = json::decode::<(u64, Vec<Object>, Game)>(&json_save_state)
+        .map_err(|e| format!("Save file doesn't match the game's types: {}", e))?;
+
+    let problems = problems(&objects, &game);
+    if problems.is_empty() {
+        println!("{} is a valid version {} save.", path, SAVE_VERSION);
+        Ok(())
+    } else {
+        Err(problems.join("\n"))
+    }
+}
+
+fn problems(objects: &[Object], game: &Game) -> Vec<String> {
+    let mut problems = vec![];
+    let (width, height) = level_size(game.dungeon_level);
+    if map_width(&game.map) != width || game.map.iter().any(|column| column.len() != height as usize) {
+        problems.push(format!("The map for dungeon level {} should be {}x{}.", game.dungeon_level, width, height));
+        return problems;
+    }
+    if objects.is_empty() || !objects[PLAYER].is_player() {
+        problems.push("The first object is not the player.".to_string());
+        return problems;
+    }
+
+    let player = &objects[PLAYER];
+    if let Some(fighter) = player.fighter {
+        if fighter.hp > player.max_hp(game) {
+            problems.push(format!("The player has {} HP but at most {}.", fighter.hp, player.max_hp(game)));
+        }
+        if player.alive != (fighter.hp > 0) {
+            problems.push(format!("The player has {} HP but is {}.", fighter.hp, if player.alive { "alive" } else { "dead" }));
+        }
+    }
+
+    for object in objects {
+        if !in_map(object.x, object.y, &game.map) {
+            problems.push(format!("{} is off the map at ({}, {}).", object.name, object.x, object.y));
+        } else if game.map[object.x as usize][object.y as usize].blocked {
+            problems.push(format!("{} is inside a wall at ({}, {}).", object.name, object.x, object.y));
+        }
+    }
+
+    let stairs = objects.iter().filter(|object| object.name == "stairs").count();
+    if stairs != 1 {
+        problems.push(format!("The level has {} stairs.", stairs));
+    }
+
+    if game.inventory.len() > INVENTORY_SIZE {
+        problems.push(format!("The inventory holds {} items but only has room for {}.", game.inventory.len(), INVENTORY_SIZE));
+    }
+    for item in &game.inventory {
+        if let Some(durability) = item.durability.filter(|d| d.current > d.max || d.current <= 0) {
+            problems.push(format!("The {} has {} durability out of {}.", item.name, durability.current, durability.max));
+        }
+    }
+    for container in objects.iter().chain(&game.inventory) {
+        if let Some(capacity) = container.container.map(|c| c.capacity) {
+            if container.inventory.len() > capacity {
+                problems.push(format!("The {} holds {} items but only has room for {}.", container.name, container.inventory.len(), capacity));
+            }
+        }
+    }
+    for slot in SLOTS {
+        let equipped = game.inventory.iter()
+            .filter(|item| item.equipment.map(|e| e.equipped && e.slot == *slot) == Some(true))
+            .count();
+        if equipped > 1 {
+            problems.push(format!("{} items are equipped on the {}.", equipped, slot));
+        }
+    }
+
+    for &item in UNIDENTIFIED_POTIONS.iter().chain(UNIDENTIFIED_SCROLLS) {
+        if item_knowledge(item, game).is_none() {
+            problems.push(format!("Nothing records whether {:?} has been identified.", item));
+        }
+    }
+    problems
+}
+
+fn give_item(name: &str, game: &mut Game) -> Result<(), String> {
+    if game.inventory.len() >= INVENTORY_SIZE {
+        return Err("The inventory is full.".into());
+    }
+    match named_item(name, 0, 0) {
+        Some(item) => {
+            game.inventory.push(item);
+            Ok(())
+        },
+        None => {
+            Err(format!("Unknown item \"{}\". Items that can be given:\n  {}", name, item_names().join("\n  ")))
+        },
+    }
+}
+
+fn set_hp(hp: i32, objects: &mut [Object], game: &Game) -> Result<(), String> {
+    let max_hp = objects[PLAYER].max_hp(game);
+    if hp < 1 || hp > max_hp {
+        return Err(format!("HP must be between 1 and {}.", max_hp));
+    }
+    match objects[PLAYER].fighter.as_mut() {
+        Some(fighter) => fighter.hp = hp,
+        None => return Err("The player has no hit points to set.".into()),
+    }
+    Ok(())
+}
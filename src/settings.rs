@@ -0,0 +1,162 @@
+// Display settings, read from settings.json in the working directory. Any key
+// left out, or the whole file, falls back to the defaults.
+
+use std::fs::File;
+use std::io::{ self, Read };
+
+use rustc_serialize::json::{ Json, Object as JsonObject };
+use tcod::console::FontLayout;
+use tcod::map::FovAlgorithm;
+
+pub const SETTINGS_PATH: &str = "settings.json";
+pub const DEFAULT_WIDTH: i32 = 80;
+pub const DEFAULT_HEIGHT: i32 = 50;
+const MIN_WIDTH: i32 = 60;
+const MIN_HEIGHT: i32 = 30;
+
+pub struct Settings {
+    pub title: String,
+    pub font: String,
+    pub font_layout: FontLayout,
+    pub greyscale: bool,
+    // Columns and rows of glyphs in the font image, for fonts that don't use
+    // one of the standard layouts' sizes.
+    pub font_size: Option<(i32, i32)>,
+    pub width: i32,
+    pub height: i32,
+    pub fullscreen: bool,
+    pub fps: i32,
+    // Glyphs to draw from somewhere else in the font image, as (glyph, column,
+    // row). With a graphical tileset this is what turns '@' into a picture.
+    pub tiles: Vec<(char, i32, i32)>,
+    // Glyphs for terrain, which is otherwise shown only by its background
+    // color. Tilesets map these to wall and floor pictures.
+    pub wall_glyph: char,
+    pub floor_glyph: char,
+    // How line of sight is worked out, for the player and for lights.
+    pub fov_algorithm: FovAlgorithm,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            title: "Tomb of the Ancient Kings".to_string(),
+            font: "arial10x10.png".to_string(),
+            font_layout: FontLayout::Tcod,
+            greyscale: true,
+            font_size: None,
+            width: DEFAULT_WIDTH,
+            height: DEFAULT_HEIGHT,
+            fullscreen: false,
+            fps: 20,
+            tiles: vec![],
+            wall_glyph: ' ',
+            floor_glyph: ' ',
+            fov_algorithm: FovAlgorithm::Basic,
+        }
+    }
+}
+
+impl Settings {
+    // A missing file is fine; one that can't be understood is an error.
+    pub fn load(path: &str) -> Result<Settings, String> {
+        let mut text = String::new();
+        match File::open(path) {
+            Ok(mut file) => file.read_to_string(&mut text).map_err(|e| e.to_string())?,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Settings::default()),
+            Err(e) => return Err(e.to_string()),
+        };
+        let json = Json::from_str(&text).map_err(|e| e.to_string())?;
+        let object = json.as_object().ok_or("expected an object of settings")?;
+        Settings::from_json(object)
+    }
+
+    pub fn from_json(object: &JsonObject) -> Result<Settings, String> {
+        let mut settings = Settings::default();
+        for (key, value) in object {
+            match key.as_str() {
+                "title" => settings.title = string(key, value)?,
+                "font" => settings.font = string(key, value)?,
+                "font_layout" => {
+                    settings.font_layout = match string(key, value)?.as_str() {
+                        "tcod" => FontLayout::Tcod,
+                        "ascii_in_row" => FontLayout::AsciiInRow,
+                        "ascii_in_col" => FontLayout::AsciiInCol,
+                        other => return Err(format!("unknown font_layout \"{}\"", other)),
+                    }
+                },
+                "greyscale" => settings.greyscale = boolean(key, value)?,
+                "font_size" => settings.font_size = Some(pair(key, value)?),
+                "width" => settings.width = number(key, value)?,
+                "height" => settings.height = number(key, value)?,
+                "fullscreen" => settings.fullscreen = boolean(key, value)?,
+                "fps" => settings.fps = number(key, value)?,
+                "tiles" => {
+                    let tiles = value.as_object().ok_or("tiles should map glyphs to [column, row]")?;
+                    for (glyph, position) in tiles {
+                        let (column, row) = pair(glyph, position)?;
+                        settings.tiles.push((glyph_of(glyph)?, column, row));
+                    }
+                },
+                "wall_glyph" => settings.wall_glyph = glyph_of(&string(key, value)?)?,
+                "floor_glyph" => settings.floor_glyph = glyph_of(&string(key, value)?)?,
+                "fov_algorithm" => settings.fov_algorithm = fov_algorithm(&string(key, value)?)?,
+                _ => return Err(format!("unknown setting \"{}\"", key)),
+            }
+        }
+
+        if settings.width < MIN_WIDTH || settings.height < MIN_HEIGHT {
+            return Err(format!("the console must be at least {}x{}", MIN_WIDTH, MIN_HEIGHT));
+        }
+        if settings.fps <= 0 {
+            return Err("fps must be positive".to_string());
+        }
+        Ok(settings)
+    }
+}
+
+fn string(key: &str, value: &Json) -> Result<String, String> {
+    value.as_string().map(String::from).ok_or_else(|| format!("{} should be a string", key))
+}
+
+fn number(key: &str, value: &Json) -> Result<i32, String> {
+    value.as_i64().map(|n| n as i32).ok_or_else(|| format!("{} should be a whole number", key))
+}
+
+fn boolean(key: &str, value: &Json) -> Result<bool, String> {
+    value.as_boolean().ok_or_else(|| format!("{} should be true or false", key))
+}
+
+fn pair(key: &str, value: &Json) -> Result<(i32, i32), String> {
+    match value.as_array().map(|array| array.iter().filter_map(Json::as_i64).collect::<Vec<_>>()) {
+        Some(ref numbers) if numbers.len() == 2 => Ok((numbers[0] as i32, numbers[1] as i32)),
+        _ => Err(format!("{} should be a pair of whole numbers", key)),
+    }
+}
+
+fn fov_algorithm(name: &str) -> Result<FovAlgorithm, String> {
+    Ok(match name {
+        "basic" => FovAlgorithm::Basic,
+        "diamond" => FovAlgorithm::Diamond,
+        "shadow" => FovAlgorithm::Shadow,
+        "permissive0" => FovAlgorithm::Permissive0,
+        "permissive1" => FovAlgorithm::Permissive1,
+        "permissive2" => FovAlgorithm::Permissive2,
+        "permissive3" => FovAlgorithm::Permissive3,
+        "permissive4" => FovAlgorithm::Permissive4,
+        "permissive5" => FovAlgorithm::Permissive5,
+        "permissive6" => FovAlgorithm::Permissive6,
+        "permissive7" => FovAlgorithm::Permissive7,
+        "permissive8" => FovAlgorithm::Permissive8,
+        "restrictive" => FovAlgorithm::Restrictive,
+        other => return Err(format!("unknown fov_algorithm \"{}\"", other)),
+    })
+}
+
+fn glyph_of(text: &str) -> Result<char, String> {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if (c as u32) < 256 => Ok(c),
+        _ => Err(format!("\"{}\" should be a single character", text)),
+    }
+}
@@ -0,0 +1,344 @@
+use std::cell::RefCell;
+use std::collections::{ HashMap, VecDeque };
+use std::rc::Rc;
+
+use tcod::input::{ Key, KeyCode };
+
+use super::*;
+
+fn key_code(code: KeyCode) -> Key {
+    Key { code, pressed: true, ..Default::default() }
+}
+
+fn key_char(c: char) -> Key {
+    Key { code: KeyCode::Char, printable: c, pressed: true, ..Default::default() }
+}
+
+fn menu_key(index: usize) -> Key {
+    key_char((b'a' + index as u8) as char)
+}
+
+// A player that is not a person. `act` returns the keys to press for its next
+// action: the first goes to `handle_keys`, the rest answer any menus it opens.
+pub trait Bot {
+    fn act(&mut self, objects: &[Object], game: &Game, fov: &Vision, stats: &mut Stats) -> Vec<Key>;
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    pub seed: u32,
+    pub class: String,
+    pub depth: u32,
+    pub level: i32,
+    pub turns: u32,
+    pub xp: i32,
+    pub cause_of_death: Option<String>,
+    pub items_used: HashMap<String, u32>,
+}
+
+// Fights whatever it can see, drinks healing when low, picks up what it finds,
+// explores each level fully and then takes the stairs. Once it heads for a
+// monster or an item it keeps going, even if that drops out of view.
+#[derive(Default)]
+pub struct SimpleBot {
+    destination: Option<(u32, (i32, i32))>,
+}
+
+impl SimpleBot {
+    fn use_item(&self, item: Item, game: &Game, stats: &mut Stats) -> Option<Vec<Key>> {
+        let index = game.inventory.iter().position(|object| object.item == Some(item))?;
+        *stats.items_used.entry(game.inventory[index].name.clone()).or_insert(0) += 1;
+        Some(vec![key_char('i'), menu_key(index)])
+    }
+
+    fn cast_lightning(&self, objects: &[Object]) -> Option<Vec<Key>> {
+        let player = &objects[PLAYER];
+        let caster = player.caster.as_ref()?;
+        let index = caster.spells.iter().position(|known| known.spell == Spell::Lightning)?;
+        let known = caster.spells[index];
+        if known.cooldown == 0 && caster.mana >= player.spell_cost(Spell::Lightning) {
+            Some(vec![key_char('z'), menu_key(index)])
+        } else {
+            None
+        }
+    }
+
+    fn head_for<F: Fn(i32, i32) -> bool>(&mut self, objects: &[Object], game: &Game, goal: F) -> Option<Vec<Key>> {
+        let (step, destination) = find_path(objects, game, goal)?;
+        self.destination = Some((game.dungeon_level, destination));
+        Some(vec![step])
+    }
+}
+
+impl Bot for SimpleBot {
+    fn act(&mut self, objects: &[Object], game: &Game, fov: &Vision, stats: &mut Stats) -> Vec<Key> {
+        let player = &objects[PLAYER];
+        let hp = player.fighter.map_or(0, |f| f.hp);
+        let max_hp = player.max_hp(game);
+
+        let monster = objects.iter()
+            .filter(|object| is_hostile(object) && fov.is_in_fov(object.x, object.y))
+            .min_by_key(|object| distance_squared(player, object.x, object.y));
+
+        if let Some(monster) = monster {
+            if hp * 3 < max_hp {
+                if let Some(keys) = self.use_item(Item::Heal, game, stats) {
+                    return keys;
+                }
+            }
+            if player.distance_to(monster) <= LIGHTNING_RANGE as f32 {
+                if let Some(keys) = self.cast_lightning(objects) {
+                    return keys;
+                }
+                if let Some(keys) = self.use_item(Item::Lightning, game, stats) {
+                    return keys;
+                }
+            }
+            if let Some(keys) = self.head_for(objects, game, |x, y| (x, y) == monster.pos()) {
+                return keys;
+            }
+        }
+
+        // Wear anything that fits an empty slot.
+        for (index, object) in game.inventory.iter().enumerate() {
+            if let Some(equipment) = object.equipment {
+                if !equipment.equipped && get_equipped_in_slot(equipment.slot, &game.inventory).is_none() {
+                    *stats.items_used.entry(object.name.clone()).or_insert(0) += 1;
+                    return vec![key_char('i'), menu_key(index)];
+                }
+            }
+        }
+
+        let has_room = game.inventory.len() < INVENTORY_SIZE;
+        let wanted = |object: &Object| match object.item {
+            Some(Item::Gold) => true,
+            Some(Item::Corpse) | None => false,
+            Some(_) => has_room,
+        };
+        let wanted_at = |x: i32, y: i32| {
+            objects.iter().any(|object| object.pos() == (x, y) && wanted(object))
+        };
+        // From a pile, pick the first wanted item out of the menu.
+        let here = items_here(objects);
+        if let Some(index) = here.iter().position(|&id| wanted(&objects[id])) {
+            return if here.len() > 1 { vec![key_char('g'), menu_key(index)] } else { vec![key_char('g')] };
+        }
+
+        if let Some((level, destination)) = self.destination.take() {
+            if level == game.dungeon_level {
+                if let Some(keys) = self.head_for(objects, game, |x, y| (x, y) == destination) {
+                    return keys;
+                }
+            }
+        }
+
+        let item_in_view = |x: i32, y: i32| fov.is_in_fov(x, y) && wanted_at(x, y);
+        if let Some(keys) = self.head_for(objects, game, item_in_view) {
+            return keys;
+        }
+
+        let unexplored = |x: i32, y: i32| pathfinding::borders_unexplored(x, y, &game.map);
+        if let Some((step, _)) = find_path(objects, game, unexplored) {
+            return vec![step];
+        }
+
+        let on_stairs = |x: i32, y: i32| objects.iter().any(|object| object.pos() == (x, y) && object.name == "stairs");
+        if on_stairs(player.x, player.y) {
+            return vec![key_char('>')];
+        }
+        match find_path(objects, game, on_stairs) {
+            Some((step, _)) => vec![step],
+            None => vec![key_code(KeyCode::NumPad5)],
+        }
+    }
+}
+
+fn distance_squared(object: &Object, x: i32, y: i32) -> i32 {
+    (object.x - x).pow(2) + (object.y - y).pow(2)
+}
+
+// The key for the first step towards the nearest tile matching `goal`, and
+// where that tile is.
+fn find_path<F: Fn(i32, i32) -> bool>(objects: &[Object], game: &Game, goal: F) -> Option<(Key, (i32, i32))> {
+    let width = map_width(&game.map);
+    let index = |(x, y): (i32, i32)| (y * width + x) as usize;
+
+    // Monsters are fair targets, but nothing else that blocks is.
+    let mut obstacle = vec![false; (width * map_height(&game.map)) as usize];
+    for object in objects.iter().skip(1) {
+        if object.blocks && !is_hostile(object) {
+            obstacle[index(object.pos())] = true;
+        }
+    }
+
+    let start = objects[PLAYER].pos();
+    let path = pathfinding::find_path(&game.map, start, |x, y| obstacle[index((x, y))], goal)?;
+    let step = path[0];
+    Some((direction_key(step.0 - start.0, step.1 - start.1), path[path.len() - 1]))
+}
+
+fn direction_key(dx: i32, dy: i32) -> Key {
+    key_code(match (dx, dy) {
+        (0, -1) => KeyCode::Up,
+        (0, 1) => KeyCode::Down,
+        (-1, 0) => KeyCode::Left,
+        (1, 0) => KeyCode::Right,
+        (-1, -1) => KeyCode::Home,
+        (1, -1) => KeyCode::PageUp,
+        (-1, 1) => KeyCode::End,
+        _ => KeyCode::PageDown,
+    })
+}
+
+// Spends new attribute and skill points on whatever the class relies on.
+fn spend_points(player: &mut Object, class: Class) {
+    let (attribute, skill) = match class {
+        Class::Warrior => (Attribute::Strength, Skill::Weapons),
+        Class::Mage => (Attribute::Intelligence, Skill::Magic),
+        Class::Rogue => (Attribute::Dexterity, Skill::Stealth),
+    };
+    while player.character.as_ref().map_or(0, |c| c.attribute_points) > 0 {
+        raise_attribute(attribute, player);
+    }
+    while player.character.as_ref().map_or(0, |c| c.skill_points) > 0 {
+        train_skill(skill, player);
+    }
+}
+
+fn total_xp(player: &Object) -> i32 {
+    let earlier_levels: i32 = (1..player.level).map(|level| LEVEL_UP_BASE + level * LEVEL_UP_FACTOR).sum();
+    earlier_levels + player.fighter.map_or(0, |f| f.xp)
+}
+
+// Plays one seeded game to the end (or to `max_turns`) with the given bot.
+pub fn simulate_game(seed: u32, class: Class, max_turns: u32, bot: &mut dyn Bot) -> Stats {
+    dice::seed(seed);
+    let keys = Rc::new(RefCell::new(VecDeque::new()));
+    let mut ui = Ui::headless(keys.clone());
+    let (mut objects, mut game) = new_game("Bot", class, &mut ui);
+    let objects = &mut objects;
+    let game = &mut game;
+    let mut stats = Stats { seed, class: class.to_string(), ..Default::default() };
+
+    // Actions that don't take a turn still count, so a stuck bot can't loop forever.
+    let mut actions = 0;
+    while stats.turns < max_turns && actions < max_turns * 2 && objects[PLAYER].alive {
+        actions += 1;
+        update_fov(&mut ui, objects, game);
+
+        keys.borrow_mut().clear();
+        keys.borrow_mut().push_back(menu_key(0));
+        level_up(objects, game, &mut ui);
+        spend_points(&mut objects[PLAYER], game.class);
+
+        keys.borrow_mut().clear();
+        let mut action_keys = bot.act(objects, game, &ui.fov, &mut stats).into_iter();
+        let key = match action_keys.next() {
+            Some(key) => key,
+            None => break,
+        };
+        keys.borrow_mut().extend(action_keys);
+
+        let player_action = handle_keys(key, &mut ui, game, objects);
+        if !objects[PLAYER].alive {
+            stats.cause_of_death = Some("their own magic".into());
+        }
+        if objects[PLAYER].alive && player_action != PlayerAction::DidntTakeTurn {
+            stats.turns += 1;
+            let killer = world_turn(objects, game, &mut ui);
+            if stats.cause_of_death.is_none() {
+                stats.cause_of_death = killer;
+            }
+        }
+    }
+
+    stats.depth = game.dungeon_level;
+    stats.level = objects[PLAYER].level;
+    stats.xp = total_xp(&objects[PLAYER]);
+    stats
+}
+
+fn usage() {
+    println!("usage: rust-rogue --simulate GAMES [--seed SEED] [--max-turns TURNS] [--class warrior|mage|rogue]");
+}
+
+// Runs `--simulate N` from the command line: N seeded games, one line of
+// stats per game and a summary at the end.
+pub fn run(args: &[String]) {
+    let mut games = 100;
+    let mut seed = 1;
+    let mut max_turns = 5000;
+    let mut class = None;
+
+    let mut args = args.iter().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().map(|value| value.to_lowercase());
+        let parsed = match (arg.as_str(), value) {
+            ("--simulate", Some(value)) => value.parse().map(|value| games = value).is_ok(),
+            ("--seed", Some(value)) => value.parse().map(|value| seed = value).is_ok(),
+            ("--max-turns", Some(value)) => value.parse().map(|value| max_turns = value).is_ok(),
+            ("--class", Some(value)) => {
+                class = CLASSES.iter().find(|class| class.to_string().to_lowercase() == value).cloned();
+                class.is_some()
+            },
+            _ => false,
+        };
+        if !parsed {
+            usage();
+            return;
+        }
+    }
+
+    println!("seed\tclass\tdepth\tlevel\tturns\txp\tcause of death");
+    let mut results = vec![];
+    for game in 0..games {
+        let game_seed = seed + game;
+        let class = class.unwrap_or(CLASSES[game_seed as usize % CLASSES.len()]);
+        let stats = simulate_game(game_seed, class, max_turns, &mut SimpleBot::default());
+        println!("{}\t{}\t{}\t{}\t{}\t{}\t{}", stats.seed, stats.class, stats.depth, stats.level, stats.turns, stats.xp,
+                 stats.cause_of_death.as_ref().map_or("survived", |cause| cause.as_str()));
+        results.push(stats);
+    }
+    print_summary(&results);
+}
+
+fn print_summary(results: &[Stats]) {
+    if results.is_empty() {
+        return;
+    }
+    let count = results.len() as f32;
+    let mean = |value: &dyn Fn(&Stats) -> f32| results.iter().map(value).sum::<f32>() / count;
+
+    println!();
+    println!("games: {}", results.len());
+    println!("mean depth: {:.2}", mean(&|stats| stats.depth as f32));
+    println!("max depth: {}", results.iter().map(|stats| stats.depth).max().unwrap_or(0));
+    println!("mean level: {:.2}", mean(&|stats| stats.level as f32));
+    println!("mean turns: {:.1}", mean(&|stats| stats.turns as f32));
+    println!("mean xp: {:.1}", mean(&|stats| stats.xp as f32));
+
+    println!("causes of death:");
+    let mut causes = HashMap::new();
+    for stats in results {
+        let cause = stats.cause_of_death.clone().unwrap_or_else(|| "survived".into());
+        *causes.entry(cause).or_insert(0) += 1;
+    }
+    print_counts(causes);
+
+    println!("items used:");
+    let mut items = HashMap::new();
+    for stats in results {
+        for (item, &uses) in &stats.items_used {
+            *items.entry(item.clone()).or_insert(0) += uses;
+        }
+    }
+    print_counts(items);
+}
+
+fn print_counts(counts: HashMap<String, u32>) {
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    for (name, count) in counts {
+        println!("  {}: {}", name, count);
+    }
+}
@@ -0,0 +1,105 @@
+// Noise and stealth. What the player does makes noise, from quiet footsteps to
+// the crack of a lightning bolt, and the noise spreads out through the level,
+// fading with every step and muffled by the rock it has to pass through.
+//
+// Monsters that haven't noticed the player yet make a perception check each
+// turn against the player's stealth, helped by what they hear and by seeing
+// the player at all, which depends on the light. Once they notice, they hunt
+// the player by sight and by sound. Until then they are open to sneak attacks.
+
+use std::collections::BinaryHeap;
+
+use super::*;
+
+pub const WALK_NOISE: i32 = 3;
+pub const ATTACK_NOISE: i32 = 12;
+pub const THUNDER_NOISE: i32 = 20;
+pub const EXPLOSION_NOISE: i32 = 16;
+// What it costs a sound to get through a wall instead of round it.
+const WALL_MUFFLING: i32 = 5;
+
+const BASE_STEALTH: i32 = 10;
+// Stealth lost standing in full light.
+const LIGHT_PENALTY: f32 = 6.0;
+// Seeing the player helps a monster notice them as much as this much noise.
+const SIGHT_BONUS: i32 = 5;
+
+pub const SNEAK_ATTACK_MULTIPLIER: i32 = 2;
+
+// How loud the player was this turn, as heard all over the level.
+pub struct Noise {
+    width: i32,
+    heard: Vec<i32>,
+}
+
+impl Noise {
+    // Spreads a noise out from where it was made. Each step costs it one point
+    // of loudness, each wall tile it goes through a few more, and it is heard
+    // wherever anything is left.
+    pub fn spread(map: &Map, (source_x, source_y): (i32, i32), loudness: i32) -> Noise {
+        let width = map_width(map);
+        let mut heard = vec![0; (width * map_height(map)) as usize];
+        // Loudest first, so a tile is settled the first time it comes off the
+        // queue, with the most noise that can reach it.
+        let mut queue = BinaryHeap::new();
+        queue.push((loudness, source_x, source_y));
+        while let Some((left, x, y)) = queue.pop() {
+            let index = (y * width + x) as usize;
+            if left <= heard[index] {
+                continue;
+            }
+            heard[index] = left;
+            for (next_x, next_y) in pathfinding::neighbours(x, y, map) {
+                let cost = if map[next_x as usize][next_y as usize].block_sight { WALL_MUFFLING } else { 1 };
+                if left - cost > heard[(next_y * width + next_x) as usize] {
+                    queue.push((left - cost, next_x, next_y));
+                }
+            }
+        }
+        Noise { width, heard }
+    }
+
+    // How much of the noise reaches (x, y), 0 if none does.
+    pub fn heard(&self, x: i32, y: i32) -> i32 {
+        let index = (y * self.width + x) as usize;
+        if x >= 0 && y >= 0 && x < self.width && index < self.heard.len() {
+            self.heard[index]
+        } else {
+            0
+        }
+    }
+}
+
+// Makes a noise this turn, unless the player has already made a louder one.
+pub fn make_noise(ui: &mut Ui, loudness: i32) {
+    ui.noise = cmp::max(ui.noise, loudness);
+}
+
+// Footsteps, and the clank of armor: every point of defense from equipment
+// makes them louder.
+pub fn walking_noise(player: &Object, game: &Game) -> i32 {
+    let armor = player.get_all_equipped(game).iter().fold(0, |sum, e| sum + cmp::max(0, e.defense_bonus));
+    WALK_NOISE + armor
+}
+
+// How hard the player is to notice: better with training and a light step,
+// worse in the light and in clanking armor.
+pub fn player_stealth(objects: &[Object], game: &Game, fov: &Vision) -> i32 {
+    let player = &objects[PLAYER];
+    let skill = player.character.as_ref().map_or(0, |c| c.stealth_bonus());
+    let armor = walking_noise(player, game) - WALK_NOISE;
+    let light = (fov.light(player.x, player.y) * LIGHT_PENALTY).round() as i32;
+    BASE_STEALTH + skill - armor - light
+}
+
+// A perception check for an unaware monster at (x, y): a d20, plus whatever
+// it hears, plus a bonus if it can see the player, against their stealth.
+pub fn perceives(x: i32, y: i32, objects: &[Object], game: &Game, fov: &Vision, noise: &Noise) -> bool {
+    let heard = noise.heard(x, y);
+    let sees = fov.notices_player(x, y);
+    if heard == 0 && !sees {
+        return false;
+    }
+    let roll = rng().gen_range(1, 21) + heard + if sees { SIGHT_BONUS } else { 0 };
+    roll > player_stealth(objects, game, fov)
+}
@@ -0,0 +1,187 @@
+// Choosing a target for a spell, with the mouse or a keyboard cursor. The
+// cursor moves with the movement keys, Tab cycles through the monsters in view
+// from nearest to farthest, and Enter or a left click confirms.
+//
+// While targeting, the line of fire to the cursor is highlighted, along with
+// the area a spell with a radius would cover. Tiles in view but out of range
+// are tinted red.
+
+use tcod::colors::lerp;
+use tcod::input::KeyCode;
+use tcod::line::Line;
+
+use super::*;
+
+const LINE_COLOR: Color = colors::YELLOW;
+const AREA_COLOR: Color = colors::ORANGE;
+const OUT_OF_RANGE_COLOR: Color = colors::DARK_RED;
+
+// A cursor over the map, moved with the mouse or the movement keys. Tab jumps
+// between the monsters in view, nearest first, and Shift-Tab goes back.
+pub struct Cursor {
+    pub position: (i32, i32),
+    monsters: Vec<usize>,
+    monster_index: Option<usize>,
+}
+
+impl Cursor {
+    // Starts on the player, or on the nearest monster in view if asked to and
+    // there is one.
+    pub fn new(objects: &[Object], fov: &Vision, on_monster: bool) -> Cursor {
+        let monsters = monsters_in_view(objects, fov);
+        let monster_index = if on_monster && !monsters.is_empty() { Some(0) } else { None };
+        Cursor {
+            position: monster_index.map_or(objects[PLAYER].pos(), |index| objects[monsters[index]].pos()),
+            monsters,
+            monster_index,
+        }
+    }
+
+    // Returns whether the key was one for the cursor.
+    pub fn handle_key(&mut self, key: Key, objects: &[Object], map: &Map) -> bool {
+        if key.code == KeyCode::Tab && !self.monsters.is_empty() {
+            let count = self.monsters.len();
+            let index = match self.monster_index {
+                Some(index) if key.shift => (index + count - 1) % count,
+                Some(index) => (index + 1) % count,
+                None => 0,
+            };
+            self.monster_index = Some(index);
+            self.position = objects[self.monsters[index]].pos();
+            return true;
+        }
+        match key_direction(key) {
+            Some((dx, dy)) if in_map(self.position.0 + dx, self.position.1 + dy, map) => {
+                self.position = (self.position.0 + dx, self.position.1 + dy);
+                true
+            },
+            _ => false,
+        }
+    }
+
+    // Follows the mouse while it is over the map.
+    pub fn handle_mouse(&mut self, mouse: Mouse, view: &MapView) {
+        if let Some(tile) = view.to_world(mouse.cx as i32, mouse.cy as i32) {
+            self.position = tile;
+        }
+    }
+}
+
+pub fn target_tile(ui: &mut Ui, objects: &[Object], game: &mut Game, max_range: Option<f32>, radius: Option<i32>) -> Option<(i32, i32)> {
+    let mut cursor = Cursor::new(objects, &ui.fov, true);
+    let mut redraw = true;
+    loop {
+        if redraw {
+            draw(ui, objects, game, cursor.position, max_range, radius);
+            ui.renderer.flush();
+        }
+        redraw = true;
+
+        let valid = |ui: &Ui, (x, y): (i32, i32)| ui.fov.is_in_fov(x, y) && in_range(objects, max_range, (x, y));
+        match ui.renderer.check_for_event() {
+            Some(Event::Mouse(mouse)) => {
+                ui.mouse = mouse;
+                cursor.handle_mouse(mouse, &ui.view);
+                if mouse.lbutton_pressed && ui.view.to_world(mouse.cx as i32, mouse.cy as i32).is_some() && valid(ui, cursor.position) {
+                    return Some(cursor.position);
+                }
+                if mouse.rbutton_pressed {
+                    return None;
+                }
+            },
+            Some(Event::Key(key)) => match key.code {
+                KeyCode::Escape => return None,
+                KeyCode::Enter | KeyCode::NumPadEnter if valid(ui, cursor.position) => return Some(cursor.position),
+                _ => redraw = cursor.handle_key(key, objects, &game.map),
+            },
+            None => {
+                ui.renderer.idle();
+                redraw = false;
+            },
+        }
+    }
+}
+
+// Keeps asking for a tile until one with a monster on it is chosen.
+pub fn target_monster(ui: &mut Ui, objects: &[Object], game: &mut Game, max_range: Option<f32>) -> Option<usize> {
+    loop {
+        let (x, y) = target_tile(ui, objects, game, max_range, None)?;
+        let monster = (0..objects.len()).find(|&id| {
+            id != PLAYER && objects[id].pos() == (x, y) && objects[id].fighter.is_some()
+        });
+        if monster.is_some() {
+            return monster;
+        }
+    }
+}
+
+// Everything with a fighter in view, nearest first.
+fn monsters_in_view(objects: &[Object], fov: &Vision) -> Vec<usize> {
+    let mut monsters: Vec<usize> = (0..objects.len())
+        .filter(|&id| id != PLAYER && objects[id].fighter.is_some() && fov.is_in_fov(objects[id].x, objects[id].y))
+        .collect();
+    monsters.sort_by_key(|&id| {
+        let (dx, dy) = (objects[id].x - objects[PLAYER].x, objects[id].y - objects[PLAYER].y);
+        dx * dx + dy * dy
+    });
+    monsters
+}
+
+fn in_range(objects: &[Object], max_range: Option<f32>, (x, y): (i32, i32)) -> bool {
+    match max_range {
+        Some(range) => objects[PLAYER].distance(x, y) <= range,
+        None => true,
+    }
+}
+
+fn draw(ui: &mut Ui, objects: &[Object], game: &Game, cursor: (i32, i32), max_range: Option<f32>, radius: Option<i32>) {
+    render_all(ui, objects, game);
+    let player = objects[PLAYER].pos();
+
+    for tile in ui.fov.visible_tiles().to_vec() {
+        if !in_range(objects, max_range, tile) {
+            tint(ui, tile, OUT_OF_RANGE_COLOR, 0.5);
+        }
+    }
+    if let Some(radius) = radius {
+        for y in cursor.1 - radius..cursor.1 + radius + 1 {
+            for x in cursor.0 - radius..cursor.0 + radius + 1 {
+                let (dx, dy) = (x - cursor.0, y - cursor.1);
+                if in_map(x, y, &game.map) && ((dx * dx + dy * dy) as f32).sqrt() <= radius as f32
+                    && effects::line_of_effect(&game.map, cursor, (x, y)) {
+                    tint(ui, (x, y), AREA_COLOR, 0.4);
+                }
+            }
+        }
+    }
+    for tile in Line::new(player, cursor).take_while(|&tile| tile != cursor) {
+        let color = if in_range(objects, max_range, tile) { LINE_COLOR } else { OUT_OF_RANGE_COLOR };
+        tint(ui, tile, color, 0.5);
+    }
+
+    let visible = ui.fov.is_in_fov(cursor.0, cursor.1);
+    let valid = visible && in_range(objects, max_range, cursor);
+    tint(ui, cursor, if valid { colors::LIGHT_YELLOW } else { colors::RED }, 0.8);
+
+    // Say what is under the cursor, on the bottom row of the map.
+    let layout = Layout::of(&*ui.renderer);
+    let names: Vec<String> = objects.iter()
+        .filter(|object| object.pos() == cursor && visible)
+        .map(|object| item_name(object, game))
+        .collect();
+    let mut status = if names.is_empty() { "Target: nothing".to_string() } else { format!("Target: {}", names.join(", ")) };
+    if !visible {
+        status.push_str(" (not in view)");
+    } else if !valid {
+        status.push_str(" (out of range)");
+    }
+    clear_rect(&mut *ui.renderer, 0, layout.panel_y - 1, layout.screen_width, 1);
+    ui.renderer.print(0, layout.panel_y - 1, &status, if valid { colors::LIGHT_YELLOW } else { colors::RED });
+}
+
+pub fn tint(ui: &mut Ui, (x, y): (i32, i32), color: Color, amount: f32) {
+    if let Some((screen_x, screen_y)) = ui.view.to_screen(x, y) {
+        let bg = lerp(ui.view.cell(x, y).bg, color, amount);
+        ui.renderer.set_background(screen_x, screen_y, bg);
+    }
+}
@@ -0,0 +1,142 @@
+// Changes to the map after it is made: doors opening, walls dug through or
+// knocked down. Every change goes through `set_tile`, which notes the tile in
+// `Game::changed_tiles` so that the field of view and the map view can catch up
+// with just that tile, the next time the field of view is updated.
+
+use tcod::line::Line;
+
+use super::*;
+
+// Blows of a pickaxe it takes to dig through ordinary rock, and through the
+// cracked walls a fireball can knock down.
+pub const ROCK_HARDNESS: i32 = 3;
+pub const CRACKED_HARDNESS: i32 = 1;
+const CRACKED_CHANCE: u32 = 5;
+const DOOR_CHANCE: u32 = 50;
+pub const DIG_RANGE: i32 = 6;
+
+pub fn set_tile(game: &mut Game, x: i32, y: i32, tile: Tile) {
+    game.map[x as usize][y as usize] = tile;
+    game.changed_tiles.push((x, y));
+}
+
+// Open ground where something used to be, keeping what the player knew of it.
+fn cleared(game: &Game, x: i32, y: i32) -> Tile {
+    let old = &game.map[x as usize][y as usize];
+    Tile { explored: old.explored, lit: old.lit, ..Tile::empty() }
+}
+
+pub fn is_closed_door(tile: &Tile) -> bool {
+    tile.door && tile.blocked
+}
+
+pub fn open_door(game: &mut Game, x: i32, y: i32) {
+    let tile = Tile { blocked: false, block_sight: false, ..game.map[x as usize][y as usize] };
+    set_tile(game, x, y, tile);
+}
+
+// The edge of the map holds everything in, so it can't be dug.
+pub fn is_diggable(map: &Map, x: i32, y: i32) -> bool {
+    let tile = &map[x as usize][y as usize];
+    tile.blocked && x > 0 && y > 0 && x < map_width(map) - 1 && y < map_height(map) - 1 && (tile.hardness > 0 || tile.door)
+}
+
+pub fn wielding_pickaxe(game: &Game) -> bool {
+    game.inventory.iter().any(|item| item.item == Some(Item::Pickaxe) && item.equipment.map_or(false, |e| e.equipped))
+}
+
+// One blow at a wall or door. Returns whether it broke through.
+pub fn dig(game: &mut Game, x: i32, y: i32) -> bool {
+    let mut tile = game.map[x as usize][y as usize];
+    tile.hardness -= 1;
+    if tile.hardness > 0 && !tile.door {
+        set_tile(game, x, y, tile);
+        return false;
+    }
+    let floor = cleared(game, x, y);
+    set_tile(game, x, y, floor);
+    true
+}
+
+// Digs a tunnel `DIG_RANGE` tiles long from the player, in the direction of a
+// tile. Returns how many tiles were dug out.
+pub fn tunnel(game: &mut Game, from: (i32, i32), towards: (i32, i32)) -> usize {
+    let mut dug = 0;
+    let far = (from.0 + (towards.0 - from.0) * DIG_RANGE, from.1 + (towards.1 - from.1) * DIG_RANGE);
+    let line: Vec<(i32, i32)> = Line::new(from, far).take(DIG_RANGE as usize).collect();
+    for (x, y) in line {
+        if !in_map(x, y, &game.map) {
+            break;
+        }
+        if is_diggable(&game.map, x, y) {
+            let floor = cleared(game, x, y);
+            set_tile(game, x, y, floor);
+            dug += 1;
+        } else if game.map[x as usize][y as usize].blocked {
+            break;
+        }
+    }
+    dug
+}
+
+// Rock a pick has been at, but not yet through.
+pub fn is_partly_dug(tile: &Tile) -> bool {
+    tile.blocked && !tile.door && !tile.cracked && tile.hardness < ROCK_HARDNESS
+}
+
+// A blast blows doors apart and knocks down cracked walls it reaches, and rock
+// dug nearly through.
+pub fn blast(game: &mut Game, (center_x, center_y): (i32, i32), radius: i32) -> usize {
+    let mut broken = vec![];
+    for y in center_y - radius..center_y + radius + 1 {
+        for x in center_x - radius..center_x + radius + 1 {
+            let (dx, dy) = (x - center_x, y - center_y);
+            if !in_map(x, y, &game.map) || ((dx * dx + dy * dy) as f32).sqrt() > radius as f32 {
+                continue;
+            }
+            let tile = &game.map[x as usize][y as usize];
+            let weak = tile.door || (tile.blocked && tile.hardness <= CRACKED_HARDNESS);
+            if weak && is_diggable(&game.map, x, y) && effects::line_of_effect(&game.map, (center_x, center_y), (x, y)) {
+                broken.push((x, y));
+            }
+        }
+    }
+    for &(x, y) in &broken {
+        let floor = cleared(game, x, y);
+        set_tile(game, x, y, floor);
+    }
+    broken.len()
+}
+
+// Where a corridor meets a room, put a door some of the time: on the room's
+// edge, between two walls.
+pub fn place_doors(rooms: &[Rect], map: &mut Map) {
+    for room in rooms {
+        let mut edge = vec![];
+        for x in room.x1 + 1..room.x2 {
+            edge.push(((x, room.y1), (1, 0)));
+            edge.push(((x, room.y2), (1, 0)));
+        }
+        for y in room.y1 + 1..room.y2 {
+            edge.push(((room.x1, y), (0, 1)));
+            edge.push(((room.x2, y), (0, 1)));
+        }
+        for ((x, y), (dx, dy)) in edge {
+            let walled = map[(x - dx) as usize][(y - dy) as usize].blocked && map[(x + dx) as usize][(y + dy) as usize].blocked;
+            let tile = &mut map[x as usize][y as usize];
+            if !tile.blocked && walled && rng().gen_range(0, 100) < DOOR_CHANCE {
+                *tile = Tile::door();
+            }
+        }
+    }
+}
+
+// Some walls are cracked, and a fireball can bring them down.
+pub fn crack_walls(map: &mut Map) {
+    for tile in map.iter_mut().flat_map(|column| column.iter_mut()) {
+        if tile.blocked && !tile.door && rng().gen_range(0, 100) < CRACKED_CHANCE {
+            tile.hardness = CRACKED_HARDNESS;
+            tile.cracked = true;
+        }
+    }
+}
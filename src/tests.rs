@@ -0,0 +1,1265 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+
+use tcod::colors;
+use tcod::input::{ Key, KeyCode };
+
+use rustc_serialize::json::Json;
+
+use super::*;
+use enchantment::{ Affix, Quality };
+use settings::Settings;
+
+// Fixtures: an open map with no walls, a plain player with no class bonuses and
+// a UI whose prompts are answered from a key queue.
+
+fn open_map() -> Map {
+    let (width, height) = level_size(1);
+    vec![vec![Tile::empty(); height as usize]; width as usize]
+}
+
+fn test_game() -> Game {
+    Game {
+        map: open_map(),
+        log: vec![],
+        inventory: vec![],
+        dungeon_level: 1,
+        item_knowledge: new_item_knowledge(),
+        gold: 0,
+        class: Class::Warrior,
+        god_mode: false,
+        changed_tiles: vec![],
+    }
+}
+
+fn fighter(hp: i32, defense: i32, power: i32, xp: i32, on_death: DeathCallback) -> Fighter {
+    Fighter { base_max_hp: hp, hp, xp, base_defense: defense, base_power: power, on_death }
+}
+
+fn test_player() -> Object {
+    let mut player = Object::new(10, 10, '@', "player", colors::WHITE, true);
+    player.alive = true;
+    player.fighter = Some(fighter(30, 2, 5, 0, DeathCallback::Player));
+    player.light = Some(Light { radius: TORCH_RADIUS, lit: true, fuel: None });
+    player
+}
+
+fn test_orc(x: i32, y: i32) -> Object {
+    let mut orc = Object::new(x, y, 'o', "orc", colors::DESATURATED_GREEN, true);
+    orc.alive = true;
+    orc.fighter = Some(fighter(10, 0, 3, 35, DeathCallback::Monster));
+    orc.ai = Some(Ai::Basic);
+    orc
+}
+
+fn key(c: char) -> Key {
+    Key { code: KeyCode::Char, printable: c, pressed: true, ..Default::default() }
+}
+
+fn key_code(code: KeyCode) -> Key {
+    Key { code, pressed: true, ..Default::default() }
+}
+
+fn headless_ui(map: &Map, keys: &[Key]) -> Ui {
+    let mut ui = Ui::headless(Rc::new(RefCell::new(keys.iter().cloned().collect::<VecDeque<_>>())));
+    initialize_fov(map, &mut ui);
+    ui
+}
+
+fn hp(object: &Object) -> i32 {
+    object.fighter.map_or(0, |f| f.hp)
+}
+
+#[test]
+fn attack_deals_power_minus_defense() {
+    let mut game = test_game();
+    let mut player = test_player();
+    let mut orc = test_orc(11, 10);
+
+    player.attack(&mut game, &mut orc);
+    assert_eq!(hp(&orc), 5);
+
+    orc.attack(&mut game, &mut player);
+    assert_eq!(hp(&player), 29);
+}
+
+#[test]
+fn attack_without_enough_power_has_no_effect() {
+    let mut game = test_game();
+    let mut player = test_player();
+    let mut orc = test_orc(11, 10);
+    player.fighter.as_mut().unwrap().base_defense = 10;
+
+    orc.attack(&mut game, &mut player);
+    assert_eq!(hp(&player), 30);
+    assert!(game.log.last().unwrap().0.contains("no effect"));
+}
+
+#[test]
+fn killing_a_monster_leaves_a_corpse_and_grants_xp() {
+    let mut game = test_game();
+    let mut player = test_player();
+    let mut orc = test_orc(11, 10);
+
+    player.attack(&mut game, &mut orc);
+    player.attack(&mut game, &mut orc);
+
+    assert!(!orc.alive);
+    assert!(orc.fighter.is_none());
+    assert!(orc.ai.is_none());
+    assert!(!orc.blocks);
+    assert_eq!(orc.item, Some(Item::Corpse));
+    assert_eq!(orc.name, "remains of orc");
+    assert_eq!(player.fighter.unwrap().xp, 35);
+}
+
+#[test]
+fn take_damage_only_reports_xp_on_death() {
+    let mut messages = vec![];
+    let mut orc = test_orc(0, 0);
+    assert_eq!(orc.take_damage(4, &mut messages), None);
+    assert_eq!(orc.take_damage(0, &mut messages), None);
+    assert_eq!(hp(&orc), 6);
+    assert_eq!(orc.take_damage(6, &mut messages), Some(35));
+}
+
+#[test]
+fn player_death_marks_the_player_but_keeps_them() {
+    let mut messages = vec![];
+    let mut player = test_player();
+    player.take_damage(100, &mut messages);
+
+    assert!(!player.alive);
+    assert!(player.fighter.is_some());
+    assert_eq!(player.char, '%');
+    assert_eq!(messages.last().unwrap().0, "You died!");
+}
+
+#[test]
+fn healing_potion_is_used_up_when_hurt() {
+    let mut game = test_game();
+    let mut objects = vec![test_player()];
+    let mut ui = headless_ui(&game.map, &[]);
+    objects[PLAYER].fighter.as_mut().unwrap().hp = 5;
+    game.inventory.push(new_item(Item::Heal, 0, 0));
+
+    use_item(0, &mut game, &mut objects, &mut ui);
+    assert!(game.inventory.is_empty());
+    assert_eq!(hp(&objects[PLAYER]), 30);
+}
+
+#[test]
+fn healing_potion_is_cancelled_at_full_health() {
+    let mut game = test_game();
+    let mut objects = vec![test_player()];
+    let mut ui = headless_ui(&game.map, &[]);
+    game.inventory.push(new_item(Item::Heal, 0, 0));
+
+    let result = cast_heal(&mut objects, &mut game, &mut ui);
+    assert_eq!(result, UseResult::Cancelled);
+
+    use_item(0, &mut game, &mut objects, &mut ui);
+    assert_eq!(game.inventory.len(), 1);
+}
+
+#[test]
+fn lightning_strikes_the_closest_visible_monster() {
+    let mut game = test_game();
+    let mut objects = vec![test_player(), test_orc(14, 10), test_orc(12, 10)];
+    let mut ui = headless_ui(&game.map, &[]);
+    update_fov(&mut ui, &mut objects, &mut game);
+
+    assert_eq!(cast_lightning(&mut objects, &mut game, &mut ui), UseResult::UsedUp);
+    assert!(!objects[2].alive);
+    assert!(objects[1].alive);
+    assert_eq!(objects[PLAYER].fighter.unwrap().xp, 35);
+}
+
+#[test]
+fn lightning_is_cancelled_without_a_target() {
+    let mut game = test_game();
+    let mut objects = vec![test_player(), test_orc(10 + LIGHTNING_RANGE + 2, 10)];
+    let mut ui = headless_ui(&game.map, &[]);
+    update_fov(&mut ui, &mut objects, &mut game);
+    game.inventory.push(new_item(Item::Lightning, 0, 0));
+
+    assert_eq!(cast_lightning(&mut objects, &mut game, &mut ui), UseResult::Cancelled);
+    use_item(0, &mut game, &mut objects, &mut ui);
+    assert_eq!(game.inventory.len(), 1);
+    assert!(objects[1].alive);
+}
+
+#[test]
+fn escaping_the_fireball_target_cancels_it() {
+    let mut game = test_game();
+    let mut objects = vec![test_player(), test_orc(12, 10)];
+    let mut ui = headless_ui(&game.map, &[]);
+    update_fov(&mut ui, &mut objects, &mut game);
+
+    assert_eq!(cast_fireball(&mut objects, &mut game, &mut ui), UseResult::Cancelled);
+    assert_eq!(hp(&objects[1]), 10);
+}
+
+#[test]
+fn fireball_targets_the_nearest_monster_from_the_keyboard() {
+    let mut game = test_game();
+    let mut objects = vec![test_player(), test_orc(18, 10), test_orc(13, 10)];
+    let mut ui = headless_ui(&game.map, &[key_code(KeyCode::Enter)]);
+    update_fov(&mut ui, &mut objects, &mut game);
+
+    assert_eq!(cast_fireball(&mut objects, &mut game, &mut ui), UseResult::UsedUp);
+    assert_eq!(hp(&objects[2]), 0);
+    assert_eq!(hp(&objects[1]), 10);
+}
+
+#[test]
+fn tab_cycles_targets_by_distance() {
+    let mut game = test_game();
+    let mut objects = vec![test_player(), test_orc(14, 10), test_orc(12, 10)];
+    let mut ui = headless_ui(&game.map, &[key_code(KeyCode::Tab), key_code(KeyCode::Enter)]);
+    update_fov(&mut ui, &mut objects, &mut game);
+
+    assert_eq!(cast_confuse(&mut objects, &mut game, &mut ui), UseResult::UsedUp);
+    assert!(matches!(objects[1].ai, Some(Ai::Confused { .. })));
+    assert_eq!(objects[2].ai, Some(Ai::Basic));
+}
+
+#[test]
+fn targets_out_of_range_cannot_be_chosen() {
+    let mut game = test_game();
+    let mut objects = vec![test_player(), test_orc(10 + CONFUSE_RANGE + 1, 10)];
+    let mut ui = headless_ui(&game.map, &[key_code(KeyCode::Enter)]);
+    update_fov(&mut ui, &mut objects, &mut game);
+
+    assert_eq!(cast_confuse(&mut objects, &mut game, &mut ui), UseResult::Cancelled);
+    assert_eq!(objects[1].ai, Some(Ai::Basic));
+}
+
+#[test]
+fn equipment_is_kept_when_used() {
+    let mut game = test_game();
+    let mut objects = vec![test_player()];
+    let mut ui = headless_ui(&game.map, &[]);
+    game.inventory.push(new_item(Item::Sword, 0, 0));
+
+    use_item(0, &mut game, &mut objects, &mut ui);
+    assert_eq!(game.inventory.len(), 1);
+    assert!(game.inventory[0].equipment.unwrap().equipped);
+}
+
+#[test]
+fn toggle_equipment_equips_and_dequips() {
+    let mut game = test_game();
+    let mut objects = vec![test_player()];
+    let mut ui = headless_ui(&game.map, &[]);
+    game.inventory.push(new_item(Item::Sword, 0, 0));
+
+    assert_eq!(toggle_equipment(0, &mut objects, &mut game, &mut ui), UseResult::UsedAndKept);
+    assert_eq!(objects[PLAYER].power(&game), 9);
+
+    assert_eq!(toggle_equipment(0, &mut objects, &mut game, &mut ui), UseResult::UsedAndKept);
+    assert!(!game.inventory[0].equipment.unwrap().equipped);
+    assert_eq!(objects[PLAYER].power(&game), 5);
+}
+
+#[test]
+fn toggle_equipment_replaces_whatever_is_in_the_slot() {
+    let mut game = test_game();
+    let mut objects = vec![test_player()];
+    let mut ui = headless_ui(&game.map, &[]);
+    game.inventory.push(new_item(Item::Sword, 0, 0));
+    game.inventory.push(new_item(Item::Sword, 0, 0));
+    game.inventory.push(new_item(Item::Shield, 0, 0));
+
+    toggle_equipment(0, &mut objects, &mut game, &mut ui);
+    toggle_equipment(2, &mut objects, &mut game, &mut ui);
+    toggle_equipment(1, &mut objects, &mut game, &mut ui);
+
+    let equipped: Vec<bool> = game.inventory.iter().map(|item| item.equipment.unwrap().equipped).collect();
+    assert_eq!(equipped, vec![false, true, true]);
+    assert_eq!(get_equipped_in_slot(Slot::RightHand, &game.inventory), Some(1));
+}
+
+#[test]
+fn toggle_equipment_cancels_for_non_equipment() {
+    let mut game = test_game();
+    let mut objects = vec![test_player()];
+    let mut ui = headless_ui(&game.map, &[]);
+    game.inventory.push(new_item(Item::Heal, 0, 0));
+
+    assert_eq!(toggle_equipment(0, &mut objects, &mut game, &mut ui), UseResult::Cancelled);
+}
+
+#[test]
+fn from_dungeon_level_uses_the_latest_transition() {
+    let table = &[Transition { level: 1, value: 10 }, Transition { level: 3, value: 20 }, Transition { level: 6, value: 30 }];
+    assert_eq!(from_dungeon_level(table, 1), 10);
+    assert_eq!(from_dungeon_level(table, 2), 10);
+    assert_eq!(from_dungeon_level(table, 3), 20);
+    assert_eq!(from_dungeon_level(table, 5), 20);
+    assert_eq!(from_dungeon_level(table, 6), 30);
+    assert_eq!(from_dungeon_level(table, 100), 30);
+}
+
+#[test]
+fn from_dungeon_level_is_zero_before_the_first_transition() {
+    assert_eq!(from_dungeon_level(&[Transition { level: 4, value: 15 }], 3), 0);
+    assert_eq!(from_dungeon_level(&[], 3), 0);
+}
+
+#[test]
+fn mut_two_returns_both_items_in_argument_order() {
+    let mut items = vec![1, 2, 3, 4];
+    {
+        let (first, second) = mut_two(0, 3, &mut items);
+        assert_eq!((*first, *second), (1, 4));
+        *first = 10;
+    }
+    {
+        let (first, second) = mut_two(2, 1, &mut items);
+        assert_eq!((*first, *second), (3, 2));
+        *second = 20;
+    }
+    assert_eq!(items, vec![10, 20, 3, 4]);
+}
+
+#[test]
+#[should_panic]
+fn mut_two_rejects_the_same_index() {
+    let mut items = vec![1, 2];
+    mut_two(1, 1, &mut items);
+}
+
+#[test]
+fn layout_splits_the_screen_between_view_and_panel() {
+    let layout = Layout::new(80, 50);
+    assert_eq!((layout.view_width, layout.view_height, layout.panel_y), (80, 43, 43));
+    assert_eq!((layout.bar_width, layout.msg_x, layout.msg_width, layout.msg_height), (20, 22, 58, 6));
+    assert_eq!(layout.inventory_width, 50);
+
+    for &(width, height) in &[(60, 30), (80, 50), (160, 90)] {
+        let layout = Layout::new(width, height);
+        assert_eq!(layout.panel_y, layout.view_height);
+        assert!(height - layout.panel_y >= 7);
+        assert_eq!(layout.msg_x + layout.msg_width, width);
+        assert!(layout.inventory_width >= 50 && layout.inventory_width <= width - 2);
+    }
+    assert_eq!(Layout::new(160, 90).inventory_width, 100);
+}
+
+fn settings_from(text: &str) -> Result<Settings, String> {
+    Settings::from_json(Json::from_str(text).unwrap().as_object().unwrap())
+}
+
+#[test]
+fn settings_fall_back_to_the_defaults() {
+    let settings = settings_from("{}").unwrap();
+    assert_eq!((settings.width, settings.height, settings.fps), (settings::DEFAULT_WIDTH, settings::DEFAULT_HEIGHT, 20));
+
+    let settings = settings_from(r#"{"width": 100, "wall_glyph": "%", "tiles": {"@": [0, 5]}}"#).unwrap();
+    assert_eq!((settings.width, settings.height), (100, settings::DEFAULT_HEIGHT));
+    assert_eq!(settings.wall_glyph, '%');
+    assert_eq!(settings.tiles, vec![('@', 0, 5)]);
+}
+
+#[test]
+fn settings_reject_unknown_keys_and_bad_values() {
+    assert_eq!(settings_from(r#"{"colour": true}"#).err(), Some("unknown setting \"colour\"".to_string()));
+    assert_eq!(settings_from(r#"{"fps": "fast"}"#).err(), Some("fps should be a whole number".to_string()));
+    assert_eq!(settings_from(r#"{"fps": 0}"#).err(), Some("fps must be positive".to_string()));
+    assert!(settings_from(r#"{"width": 60, "height": 30}"#).is_ok());
+    assert_eq!(settings_from(r#"{"width": 59}"#).err(), Some("the console must be at least 60x30".to_string()));
+    assert!(settings_from(r#"{"height": 29}"#).is_err());
+}
+
+#[test]
+fn gain_level_needs_enough_xp() {
+    let mut game = test_game();
+    let mut player = test_player();
+    player.level = 1;
+    let threshold = LEVEL_UP_BASE + LEVEL_UP_FACTOR;
+
+    player.fighter.as_mut().unwrap().xp = threshold - 1;
+    assert!(!gain_level(&mut player, &mut game));
+    assert_eq!(player.level, 1);
+
+    player.fighter.as_mut().unwrap().xp = threshold + 5;
+    assert!(gain_level(&mut player, &mut game));
+    assert_eq!(player.level, 2);
+    assert_eq!(player.fighter.unwrap().xp, 5);
+    assert_eq!(player.fighter.unwrap().base_max_hp, 30 + game.class.hp_per_level());
+}
+
+#[test]
+fn gain_level_hands_out_character_points() {
+    let mut game = test_game();
+    let mut player = test_player();
+    player.level = 1;
+    player.character = Some(game.class.character());
+    player.fighter.as_mut().unwrap().xp = LEVEL_UP_BASE + LEVEL_UP_FACTOR;
+
+    gain_level(&mut player, &mut game);
+    let character = player.character.as_ref().unwrap();
+    assert_eq!((character.attribute_points, character.skill_points), (1, 1));
+}
+
+#[test]
+fn level_up_asks_for_a_perk_every_few_levels() {
+    let mut game = test_game();
+    let mut objects = vec![test_player()];
+    objects[PLAYER].level = PERK_LEVEL_INTERVAL - 1;
+    objects[PLAYER].character = Some(game.class.character());
+    objects[PLAYER].fighter.as_mut().unwrap().xp = LEVEL_UP_BASE + (PERK_LEVEL_INTERVAL - 1) * LEVEL_UP_FACTOR;
+    let mut ui = headless_ui(&game.map, &[key('a')]);
+
+    level_up(&mut objects, &mut game, &mut ui);
+    assert_eq!(objects[PLAYER].level, PERK_LEVEL_INTERVAL);
+    let perk = game.class.perks()[0];
+    assert_eq!(objects[PLAYER].character.as_ref().unwrap().perk_count(perk), 1);
+}
+
+// Flood fills the floor from (x, y), returning which tiles were reached.
+fn reachable_from(map: &Map, x: i32, y: i32) -> Vec<Vec<bool>> {
+    let mut reached = vec![vec![false; map_height(map) as usize]; map_width(map) as usize];
+    let mut stack = vec![(x, y)];
+    while let Some((x, y)) = stack.pop() {
+        if !in_map(x, y, map) {
+            continue;
+        }
+        // Closed doors open when walked into.
+        let tile = &map[x as usize][y as usize];
+        if (tile.blocked && !tile.door) || reached[x as usize][y as usize] {
+            continue;
+        }
+        reached[x as usize][y as usize] = true;
+        stack.extend(&[(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]);
+    }
+    reached
+}
+
+fn generated_levels() -> Vec<(Map, Vec<Object>)> {
+    let mut levels = vec![];
+    for seed in 1..20 {
+        for &level in &[1, 4, 8] {
+            dice::seed(seed);
+            let mut objects = vec![test_player()];
+            let map = make_map(level, &mut objects);
+            levels.push((map, objects));
+        }
+    }
+    levels
+}
+
+#[test]
+fn make_map_connects_every_floor_tile() {
+    for (map, objects) in generated_levels() {
+        let reached = reachable_from(&map, objects[PLAYER].x, objects[PLAYER].y);
+        for x in 0..map_width(&map) as usize {
+            for y in 0..map_height(&map) as usize {
+                assert!((map[x][y].blocked && !map[x][y].door) || reached[x][y], "floor at ({}, {}) is cut off", x, y);
+            }
+        }
+    }
+}
+
+#[test]
+fn make_map_places_reachable_stairs() {
+    for (map, objects) in generated_levels() {
+        let stairs: Vec<&Object> = objects.iter().filter(|object| object.name == "stairs").collect();
+        assert_eq!(stairs.len(), 1);
+        let reached = reachable_from(&map, objects[PLAYER].x, objects[PLAYER].y);
+        assert!(reached[stairs[0].x as usize][stairs[0].y as usize]);
+    }
+}
+
+#[test]
+fn make_map_spawns_nothing_in_walls() {
+    for (map, objects) in generated_levels() {
+        for object in &objects {
+            assert!(!map[object.x as usize][object.y as usize].blocked, "{} spawned in a wall", object.name);
+        }
+    }
+}
+
+#[test]
+fn make_map_never_stacks_blocking_objects() {
+    for (_, objects) in generated_levels() {
+        for (id, object) in objects.iter().enumerate().filter(|&(_, object)| object.blocks) {
+            let stacked = objects.iter().skip(id + 1).any(|other| other.blocks && other.pos() == object.pos());
+            assert!(!stacked, "{} shares a tile with another blocking object", object.name);
+        }
+    }
+}
+
+#[test]
+fn make_map_is_the_same_for_the_same_seed() {
+    dice::seed(7);
+    let first = make_map(3, &mut vec![test_player()]);
+    dice::seed(7);
+    let second = make_map(3, &mut vec![test_player()]);
+    let walls = |map: &Map| map.iter().map(|column| column.iter().map(|tile| tile.blocked).collect::<Vec<_>>()).collect::<Vec<_>>();
+    assert_eq!(walls(&first), walls(&second));
+}
+
+fn travel(ui: &mut Ui, objects: &mut [Object], game: &mut Game) -> u32 {
+    let mut turns = 0;
+    update_fov(ui, objects, game);
+    while travel::step(ui, objects, game) == PlayerAction::TookTurn {
+        update_fov(ui, objects, game);
+        turns += 1;
+    }
+    turns
+}
+
+#[test]
+fn travel_walks_to_the_destination() {
+    let mut game = test_game();
+    reveal_map(&mut game);
+    let mut objects = vec![test_player()];
+    let mut ui = headless_ui(&game.map, &[]);
+    travel::start(Goal::Tile(20, 15), &mut ui, &objects, &mut game);
+    assert_eq!(travel(&mut ui, &mut objects, &mut game), 10);
+    assert_eq!(objects[PLAYER].pos(), (20, 15));
+    assert!(ui.travel.is_none());
+}
+
+#[test]
+fn travel_stops_when_a_monster_comes_into_view() {
+    let mut game = test_game();
+    reveal_map(&mut game);
+    let mut objects = vec![test_player(), test_orc(50, 10)];
+    let mut ui = headless_ui(&game.map, &[]);
+    travel::start(Goal::Tile(60, 10), &mut ui, &objects, &mut game);
+    travel(&mut ui, &mut objects, &mut game);
+    assert!(ui.travel.is_none());
+    assert!(ui.fov.is_in_fov(50, 10));
+    assert!(objects[PLAYER].distance_to(&objects[1]) > TORCH_RADIUS as f32 - 1.0);
+}
+
+#[test]
+fn travel_stops_when_an_item_comes_into_view() {
+    let mut game = test_game();
+    reveal_map(&mut game);
+    let mut objects = vec![test_player(), new_item(Item::Sword, 50, 10)];
+    let mut ui = headless_ui(&game.map, &[]);
+    travel::start(Goal::Tile(60, 10), &mut ui, &objects, &mut game);
+    travel(&mut ui, &mut objects, &mut game);
+    assert!(ui.fov.is_in_fov(50, 10));
+    assert!(objects[PLAYER].x < 50);
+}
+
+#[test]
+fn running_stops_where_the_surroundings_change() {
+    let mut game = test_game();
+    for y in 0..20 {
+        game.map[30][y] = Tile::wall();
+    }
+    reveal_map(&mut game);
+    let mut objects = vec![test_player()];
+    let mut ui = headless_ui(&game.map, &[]);
+    travel::start(Goal::Run(1, 0), &mut ui, &objects, &mut game);
+    assert_eq!(travel(&mut ui, &mut objects, &mut game), 19);
+    assert_eq!(objects[PLAYER].pos(), (29, 10));
+}
+
+#[test]
+fn exploring_visits_everything_reachable() {
+    let mut game = test_game();
+    let mut objects = vec![test_player()];
+    let mut ui = headless_ui(&game.map, &[]);
+    travel::start(Goal::Explore, &mut ui, &objects, &mut game);
+    travel(&mut ui, &mut objects, &mut game);
+    assert!(game.map.iter().all(|column| column.iter().all(|tile| tile.explored)));
+}
+
+fn description_text(lines: Vec<(String, colors::Color)>) -> String {
+    lines.into_iter().map(|(line, _)| line).collect::<Vec<_>>().join("\n")
+}
+
+#[test]
+fn looking_at_a_monster_describes_its_health_and_status() {
+    let mut game = test_game();
+    let mut orc = test_orc(12, 10);
+    orc.fighter.as_mut().unwrap().hp = 4;
+    orc.ai = Some(Ai::Confused { previous_ai: Box::new(Ai::Basic), num_turns: 3 });
+    let mut objects = vec![test_player(), orc];
+    let mut ui = headless_ui(&game.map, &[]);
+    update_fov(&mut ui, &mut objects, &mut game);
+
+    let text = description_text(look::describe_tile((12, 10), &objects, &game, &ui.fov));
+    assert!(text.starts_with("You see:"));
+    assert!(text.contains("Orc"));
+    assert!(text.contains("Badly wounded (4/10 HP)"));
+    assert!(text.contains("Confused for 3 more turns"));
+    assert!(text.contains("Floor"));
+}
+
+#[test]
+fn items_are_remembered_only_once_seen_where_they_lie() {
+    let mut game = test_game();
+    reveal_map(&mut game);
+    let mut objects = vec![test_player(), new_item(Item::Sword, 12, 10)];
+    let mut ui = headless_ui(&game.map, &[]);
+    update_fov(&mut ui, &mut objects, &mut game);
+    assert!(objects[1].seen);
+    assert!(description_text(look::describe_tile((12, 10), &objects, &game, &ui.fov)).contains("Sword"));
+
+    objects[1].set_pos(40, 10);
+    update_fov(&mut ui, &mut objects, &mut game);
+    assert!(!ui.fov.is_in_fov(40, 10));
+    assert!(!description_text(look::describe_tile((40, 10), &objects, &game, &ui.fov)).contains("Sword"));
+}
+
+#[test]
+fn looking_out_of_sight_describes_only_what_is_remembered() {
+    let mut game = test_game();
+    reveal_map(&mut game);
+    let mut objects = vec![test_player(), test_orc(40, 10), new_item(Item::Sword, 40, 10)];
+    let mut ui = headless_ui(&game.map, &[]);
+    update_fov(&mut ui, &mut objects, &mut game);
+    let text = description_text(look::describe_tile((40, 10), &objects, &game, &ui.fov));
+    assert!(!text.contains("Sword"));
+
+    objects[2].seen = true;
+    let text = description_text(look::describe_tile((40, 10), &objects, &game, &ui.fov));
+    assert!(text.starts_with("You remember:"));
+    assert!(text.contains("Sword"));
+    assert!(text.contains("+4 attack"));
+    assert!(!text.contains("Orc"));
+}
+
+#[test]
+fn looking_at_an_unidentified_potion_gives_nothing_away() {
+    let mut game = test_game();
+    let mut objects = vec![test_player(), new_item(Item::Heal, 11, 10)];
+    let mut ui = headless_ui(&game.map, &[]);
+    update_fov(&mut ui, &mut objects, &mut game);
+
+    let text = description_text(look::describe_tile((11, 10), &objects, &game, &ui.fov));
+    assert!(text.contains("A potion you haven't identified."));
+    assert!(!text.contains("heal"));
+}
+
+#[test]
+fn monsters_notice_a_player_in_the_dark_only_from_next_to_them() {
+    let mut game = test_game();
+    let mut objects = vec![test_player(), test_orc(14, 10)];
+    let mut ui = headless_ui(&game.map, &[]);
+    update_fov(&mut ui, &mut objects, &mut game);
+    assert!(ui.fov.notices_player(14, 10));
+
+    lighting::toggle_light(&mut objects, &mut game);
+    update_fov(&mut ui, &mut objects, &mut game);
+    assert!(!ui.fov.notices_player(14, 10));
+    assert!(!ui.fov.is_in_fov(14, 10));
+    assert!(ui.fov.notices_player(11, 10));
+}
+
+#[test]
+fn lit_rooms_can_be_seen_from_beyond_the_light_of_a_torch() {
+    let mut game = test_game();
+    for x in 25..30 {
+        game.map[x][10].lit = true;
+    }
+    let mut objects = vec![test_player(), test_orc(27, 10)];
+    let mut ui = headless_ui(&game.map, &[]);
+    update_fov(&mut ui, &mut objects, &mut game);
+    assert!(ui.fov.is_in_fov(27, 10));
+    assert!(!ui.fov.is_in_fov(22, 10));
+}
+
+#[test]
+fn lanterns_light_further_until_their_fuel_runs_out() {
+    let mut game = test_game();
+    let objects = vec![test_player()];
+    let mut lantern = new_item(Item::Lantern, 0, 0);
+    lantern.equipment.as_mut().unwrap().equipped = true;
+    lantern.light.as_mut().unwrap().fuel = Some(2);
+    game.inventory.push(lantern);
+    assert_eq!(lighting::player_light_radius(&objects[PLAYER], &game), lighting::LANTERN_RADIUS);
+
+    lighting::burn_fuel(&objects, &mut game);
+    lighting::burn_fuel(&objects, &mut game);
+    assert_eq!(lighting::player_light_radius(&objects[PLAYER], &game), TORCH_RADIUS);
+    assert!(game.log.last().unwrap().0.contains("goes out"));
+}
+
+#[test]
+fn sneak_attacks_deal_extra_damage_and_wake_the_monster() {
+    let mut game = test_game();
+    let mut objects = vec![test_player(), test_orc(11, 10)];
+    objects[1].ai = Some(Ai::Unaware);
+    objects[1].fighter.as_mut().unwrap().hp = 20;
+    let (player, orc) = mut_two(PLAYER, 1, &mut objects);
+    player.attack(&mut game, orc);
+    assert_eq!(orc.fighter.unwrap().hp, 20 - 5 * stealth::SNEAK_ATTACK_MULTIPLIER);
+    assert_eq!(orc.ai, Some(Ai::Basic));
+    assert!(game.log.iter().any(|message| message.0.contains("unaware")));
+}
+
+#[test]
+fn a_sneak_attack_that_does_no_damage_still_wakes_the_monster() {
+    let mut game = test_game();
+    let mut objects = vec![test_player(), test_orc(11, 10)];
+    objects[1].ai = Some(Ai::Unaware);
+    objects[1].fighter.as_mut().unwrap().base_defense = 100;
+    let (player, orc) = mut_two(PLAYER, 1, &mut objects);
+    player.attack(&mut game, orc);
+    assert_eq!(orc.fighter.unwrap().hp, 10);
+    assert_eq!(orc.ai, Some(Ai::Basic));
+}
+
+#[test]
+fn walking_into_a_wall_makes_no_noise() {
+    let mut game = walled_in_game();
+    let mut objects = vec![test_player()];
+    objects[PLAYER].set_pos(11, 10);
+    let mut ui = headless_ui(&game.map, &[]);
+    player_move_or_attack(1, 0, &mut game, &mut objects, &mut ui);
+    assert_eq!(objects[PLAYER].pos(), (11, 10));
+    assert_eq!(ui.noise, 0);
+    player_move_or_attack(-1, 0, &mut game, &mut objects, &mut ui);
+    assert_eq!(ui.noise, stealth::walking_noise(&objects[PLAYER], &game));
+}
+
+#[test]
+fn walls_muffle_noise() {
+    let mut game = test_game();
+    for y in 0..20 {
+        game.map[15][y] = Tile::wall();
+    }
+    let noise = Noise::spread(&game.map, (10, 10), stealth::ATTACK_NOISE);
+    assert_eq!(noise.heard(14, 10), stealth::ATTACK_NOISE - 4);
+    assert_eq!(noise.heard(5, 10), stealth::ATTACK_NOISE - 5);
+    assert!(noise.heard(16, 10) < noise.heard(4, 10));
+    assert_eq!(noise.heard(40, 10), 0);
+}
+
+#[test]
+fn unaware_monsters_notice_noise_but_not_a_quiet_player_in_the_dark() {
+    let mut game = test_game();
+    let mut objects = vec![test_player(), test_orc(16, 10)];
+    objects[1].ai = Some(Ai::Unaware);
+    objects[PLAYER].light = None;
+    let mut ui = headless_ui(&game.map, &[]);
+    update_fov(&mut ui, &mut objects, &mut game);
+
+    let footsteps = Noise::spread(&game.map, (10, 10), stealth::WALK_NOISE);
+    for _ in 0..20 {
+        ai_take_turn(1, &mut game, &mut objects, &ui.fov, &footsteps);
+    }
+    assert_eq!(objects[1].ai, Some(Ai::Unaware));
+
+    let thunder = Noise::spread(&game.map, (10, 10), stealth::THUNDER_NOISE);
+    ai_take_turn(1, &mut game, &mut objects, &ui.fov, &thunder);
+    assert_eq!(objects[1].ai, Some(Ai::Basic));
+}
+
+#[test]
+fn walls_shelter_monsters_from_the_fireball_blast() {
+    let mut game = test_game();
+    for y in 0..20 {
+        game.map[17][y] = Tile::wall();
+    }
+    let mut objects = vec![test_player(), test_orc(15, 10), test_orc(18, 10)];
+    let mut ui = headless_ui(&game.map, &[key_code(KeyCode::Enter)]);
+    update_fov(&mut ui, &mut objects, &mut game);
+
+    assert_eq!(cast_fireball(&mut objects, &mut game, &mut ui), UseResult::UsedUp);
+    assert_eq!(hp(&objects[1]), 0);
+    assert_eq!(hp(&objects[2]), 10);
+    assert!(matches!(game.map[14][10].effect, Some(Effect::Fire { .. })));
+    assert_eq!(game.map[18][10].effect, None);
+}
+
+#[test]
+fn fire_burns_out_on_stone_and_spreads_over_brush() {
+    dice::seed(3);
+    let mut game = test_game();
+    for x in 20..25 {
+        game.map[x][10].flammable = true;
+    }
+    let mut objects = vec![test_player()];
+    let ui = headless_ui(&game.map, &[]);
+    effects::ignite(&mut game.map, 12, 12);
+    effects::ignite(&mut game.map, 20, 10);
+    for _ in 0..30 {
+        effects::update(&mut objects, &mut game, &ui.fov);
+    }
+    assert!((20..25).all(|x| !game.map[x][10].flammable));
+    assert!(game.map.iter().all(|column| column.iter().all(|tile| tile.effect.is_none())));
+}
+
+#[test]
+fn poison_gas_drifts_thins_and_hurts() {
+    let mut game = test_game();
+    for y in 0..20 {
+        game.map[15][y] = Tile::wall();
+    }
+    let mut objects = vec![test_player(), test_orc(13, 10)];
+    let ui = headless_ui(&game.map, &[]);
+    effects::release_gas(&mut game.map, (13, 10), 1);
+    effects::update(&mut objects, &mut game, &ui.fov);
+    assert_eq!(hp(&objects[1]), 8);
+    assert!(matches!(game.map[11][10].effect, Some(Effect::Gas { .. })));
+    assert_eq!(game.map[16][10].effect, None);
+    for _ in 0..effects::POISON_DENSITY {
+        effects::update(&mut objects, &mut game, &ui.fov);
+    }
+    assert!(game.map.iter().all(|column| column.iter().all(|tile| tile.effect.is_none())));
+}
+
+#[test]
+fn water_spreads_out_and_puts_out_fire() {
+    let mut game = test_game();
+    game.map = vec![vec![Tile::wall(); 20]; 40];
+    for x in 29..33 {
+        game.map[x][10] = Tile::empty();
+    }
+    let mut objects = vec![test_player()];
+    objects[PLAYER].set_pos(29, 10);
+    let ui = headless_ui(&game.map, &[]);
+    game.map[30][10].effect = Some(Effect::Water { depth: 4 });
+    effects::ignite(&mut game.map, 31, 10);
+    for _ in 0..5 {
+        effects::update(&mut objects, &mut game, &ui.fov);
+    }
+    let depth = |game: &Game, x: usize| match game.map[x][10].effect {
+        Some(Effect::Water { depth }) => depth,
+        _ => 0,
+    };
+    assert_eq!((29..33).map(|x| depth(&game, x)).sum::<i32>(), 4);
+    assert!((29..32).all(|x| (depth(&game, x) - depth(&game, x + 1)).abs() <= 1));
+    assert!(depth(&game, 31) > 0);
+    effects::ignite(&mut game.map, 31, 10);
+    assert!(matches!(game.map[31][10].effect, Some(Effect::Water { .. })));
+}
+
+fn walled_in_game() -> Game {
+    let mut game = test_game();
+    for y in 0..20 {
+        game.map[12][y] = Tile::wall();
+    }
+    game
+}
+
+#[test]
+fn digging_through_rock_opens_up_the_view() {
+    let mut game = walled_in_game();
+    let mut pickaxe = new_item(Item::Pickaxe, 0, 0);
+    pickaxe.equipment.as_mut().unwrap().equipped = true;
+    game.inventory.push(pickaxe);
+    let mut objects = vec![test_player()];
+    objects[PLAYER].set_pos(11, 10);
+    let mut ui = headless_ui(&game.map, &[]);
+    update_fov(&mut ui, &mut objects, &mut game);
+    assert!(!ui.fov.is_in_fov(14, 10));
+
+    for _ in 0..terrain::ROCK_HARDNESS {
+        assert!(game.map[12][10].blocked);
+        player_move_or_attack(1, 0, &mut game, &mut objects, &mut ui);
+        assert!(game.changed_tiles.contains(&(12, 10)));
+        assert!(!game.map[12][10].blocked || terrain::is_partly_dug(&game.map[12][10]));
+        assert!(!game.map[12][10].cracked);
+    }
+    assert!(!game.map[12][10].blocked);
+    assert_eq!(objects[PLAYER].pos(), (11, 10));
+    update_fov(&mut ui, &mut objects, &mut game);
+    assert!(ui.fov.is_in_fov(14, 10));
+}
+
+#[test]
+fn walking_into_a_door_opens_it() {
+    let mut game = test_game();
+    game.map[11][10] = Tile::door();
+    let mut objects = vec![test_player()];
+    let mut ui = headless_ui(&game.map, &[]);
+    player_move_or_attack(1, 0, &mut game, &mut objects, &mut ui);
+    assert!(!game.map[11][10].blocked);
+    assert_eq!(objects[PLAYER].pos(), (10, 10));
+    player_move_or_attack(1, 0, &mut game, &mut objects, &mut ui);
+    assert_eq!(objects[PLAYER].pos(), (11, 10));
+}
+
+#[test]
+fn the_dig_spell_tunnels_through_rock() {
+    let mut game = walled_in_game();
+    for x in 13..15 {
+        game.map[x][10] = Tile::wall();
+    }
+    let mut objects = vec![test_player()];
+    objects[PLAYER].set_pos(11, 10);
+    let mut ui = headless_ui(&game.map, &[key_code(KeyCode::Right), key_code(KeyCode::Enter)]);
+    update_fov(&mut ui, &mut objects, &mut game);
+
+    assert_eq!(cast_dig(&mut objects, &mut game, &mut ui), UseResult::UsedUp);
+    assert!((12..15).all(|x| !game.map[x][10].blocked));
+    assert!(game.map[12][9].blocked);
+
+    let mut ui = headless_ui(&game.map, &[key_code(KeyCode::Left), key_code(KeyCode::Enter)]);
+    update_fov(&mut ui, &mut objects, &mut game);
+    assert_eq!(cast_dig(&mut objects, &mut game, &mut ui), UseResult::Cancelled);
+}
+
+#[test]
+fn fireballs_knock_down_cracked_walls_and_doors() {
+    let mut game = test_game();
+    for y in 0..20 {
+        game.map[17][y] = Tile::wall();
+    }
+    game.map[17][9].hardness = terrain::CRACKED_HARDNESS;
+    game.map[17][9].cracked = true;
+    game.map[14][12] = Tile::door();
+    let mut objects = vec![test_player(), test_orc(15, 10)];
+    let mut ui = headless_ui(&game.map, &[key_code(KeyCode::Enter)]);
+    update_fov(&mut ui, &mut objects, &mut game);
+
+    assert_eq!(cast_fireball(&mut objects, &mut game, &mut ui), UseResult::UsedUp);
+    assert!(!game.map[17][9].blocked);
+    assert!(game.map[17][11].blocked);
+    assert!(!game.map[14][12].door);
+}
+
+fn test_chest(x: i32, y: i32, contents: Vec<Object>) -> Object {
+    let mut chest = containers::new_chest(1, x, y);
+    chest.container = Some(Container { capacity: 20, locked: false, trap: None });
+    chest.inventory = contents;
+    chest.gold = 0;
+    chest
+}
+
+#[test]
+fn bags_hold_items_in_a_single_inventory_slot() {
+    let mut game = test_game();
+    game.inventory = vec![new_item(Item::Bag, 0, 0), new_item(Item::Heal, 0, 0), new_item(Item::Bag, 0, 0)];
+    let mut objects = vec![test_player()];
+
+    // Put the potion in, then try the other bag.
+    let mut ui = headless_ui(&game.map, &[key('a'), key('a'), key('b'), key('a')]);
+    use_item(0, &mut game, &mut objects, &mut ui);
+    assert_eq!(game.inventory.len(), 2);
+    assert_eq!(game.inventory[0].inventory[0].item, Some(Item::Heal));
+    assert_eq!(game.inventory[1].item, Some(Item::Bag));
+    assert!(game.log.iter().any(|(message, _)| message.contains("won't fit")));
+
+    let mut ui = headless_ui(&game.map, &[key('a')]);
+    use_item(0, &mut game, &mut objects, &mut ui);
+    assert!(game.inventory[0].inventory.is_empty());
+    assert_eq!(game.inventory[2].item, Some(Item::Heal));
+}
+
+#[test]
+fn opening_a_trapped_chest_springs_the_trap_once() {
+    let mut game = test_game();
+    let mut chest = test_chest(11, 10, vec![new_item(Item::Heal, 11, 10)]);
+    chest.container = Some(Container { capacity: 20, locked: false, trap: Some(containers::Trap::Fire) });
+    chest.gold = 25;
+    let mut objects = vec![test_player(), chest];
+
+    let mut ui = headless_ui(&game.map, &[key('a')]);
+    player_move_or_attack(1, 0, &mut game, &mut objects, &mut ui);
+    assert_eq!(objects[PLAYER].pos(), (10, 10));
+    assert!(matches!(game.map[10][10].effect, Some(Effect::Fire { .. })));
+    assert_eq!(game.gold, 25);
+    assert_eq!(game.inventory[0].item, Some(Item::Heal));
+    assert_eq!(objects[1].container.unwrap().trap, None);
+}
+
+#[test]
+fn locked_chests_open_once_the_lock_is_picked() {
+    dice::seed(3);
+    let mut game = test_game();
+    let mut chest = test_chest(11, 10, vec![new_item(Item::Heal, 11, 10)]);
+    chest.container = Some(Container { capacity: 20, locked: true, trap: None });
+    let mut objects = vec![test_player(), chest];
+
+    let mut tries = 0;
+    while objects[1].container.unwrap().locked {
+        let mut ui = headless_ui(&game.map, &[key('a')]);
+        player_move_or_attack(1, 0, &mut game, &mut objects, &mut ui);
+        tries += 1;
+        assert!(tries < 100);
+    }
+    assert_eq!(game.inventory.len(), 1);
+    assert!(objects[1].inventory.is_empty());
+}
+
+#[test]
+fn nested_containers_survive_a_save() {
+    let mut bag = new_item(Item::Bag, 0, 0);
+    bag.inventory.push(new_item(Item::Confuse, 0, 0));
+    let objects = vec![test_player(), test_chest(11, 10, vec![bag, new_item(Item::Heal, 0, 0)])];
+
+    let json = rustc_serialize::json::encode(&objects).unwrap();
+    let loaded: Vec<Object> = rustc_serialize::json::decode(&json).unwrap();
+    let chest = &loaded[1];
+    assert_eq!(chest.inventory.len(), 2);
+    assert_eq!(chest.inventory[0].container.map(|c| c.capacity), Some(containers::BAG_CAPACITY));
+    assert_eq!(chest.inventory[0].inventory[0].item, Some(Item::Confuse));
+}
+
+#[test]
+fn picking_up_nothing_takes_no_turn() {
+    let mut game = test_game();
+    let mut objects = vec![test_player()];
+    let mut ui = headless_ui(&game.map, &[]);
+    assert_eq!(handle_keys(key('g'), &mut ui, &mut game, &mut objects), PlayerAction::DidntTakeTurn);
+    assert_eq!(game.log.last().unwrap().0, "There is nothing here to pick up.");
+
+    objects.push(new_item(Item::Heal, 10, 10));
+    game.inventory = (0..INVENTORY_SIZE).map(|_| new_item(Item::Heal, 0, 0)).collect();
+    assert_eq!(handle_keys(key('g'), &mut ui, &mut game, &mut objects), PlayerAction::DidntTakeTurn);
+    assert_eq!(objects.len(), 2);
+}
+
+#[test]
+fn the_pickup_menu_takes_one_item_or_the_whole_pile() {
+    let mut game = test_game();
+    let mut objects = vec![test_player(), new_item(Item::Heal, 10, 10), new_item(Item::Sword, 10, 10), new_gold_pile(12, 10, 10)];
+
+    let mut ui = headless_ui(&game.map, &[key('b')]);
+    assert_eq!(handle_keys(key('g'), &mut ui, &mut game, &mut objects), PlayerAction::TookTurn);
+    assert_eq!(game.inventory.len(), 1);
+    assert_eq!(game.inventory[0].item, Some(Item::Sword));
+
+    let mut ui = headless_ui(&game.map, &[key('c')]);
+    assert_eq!(handle_keys(key('g'), &mut ui, &mut game, &mut objects), PlayerAction::TookTurn);
+    assert_eq!(objects.len(), 1);
+    assert_eq!(game.inventory[1].item, Some(Item::Heal));
+    assert_eq!(game.gold, 12);
+
+    // Cancelling the menu takes no turn.
+    objects.push(new_item(Item::Heal, 10, 10));
+    objects.push(new_item(Item::Heal, 10, 10));
+    let mut ui = headless_ui(&game.map, &[]);
+    assert_eq!(handle_keys(key('g'), &mut ui, &mut game, &mut objects), PlayerAction::DidntTakeTurn);
+}
+
+#[test]
+fn piles_of_items_are_drawn_as_a_count() {
+    let mut game = test_game();
+    let mut objects = vec![test_player(), new_item(Item::Heal, 11, 10), new_item(Item::Sword, 11, 10), new_item(Item::Shield, 12, 10)];
+    let mut ui = headless_ui(&game.map, &[]);
+    update_fov(&mut ui, &mut objects, &mut game);
+    render_all(&mut ui, &objects, &game);
+    assert_eq!(ui.view.cell(11, 10).c, '2');
+    assert_eq!(ui.view.cell(12, 10).c, '[');
+}
+
+fn enchanted(item: Item, enchantment: Enchantment) -> Object {
+    let mut object = new_item(item, 0, 0);
+    enchantment::apply(&mut object, enchantment);
+    object
+}
+
+fn magic(bonus: i32, prefix: Option<Affix>, suffix: Option<Affix>, cursed: bool) -> Enchantment {
+    Enchantment { quality: Quality::Common, bonus, prefix, suffix, cursed, identified: false }
+}
+
+#[test]
+fn cursed_items_stay_on_until_the_curse_is_lifted() {
+    let mut game = test_game();
+    let mut objects = vec![test_player()];
+    let mut ui = headless_ui(&game.map, &[]);
+    game.inventory.push(enchanted(Item::Sword, magic(-2, None, None, true)));
+    game.inventory.push(new_item(Item::RemoveCurse, 0, 0));
+
+    use_item(0, &mut game, &mut objects, &mut ui);
+    assert_eq!(objects[PLAYER].power(&game), 7);
+    assert_eq!(item_name(&game.inventory[0], &game), "cursed sword -2");
+
+    use_item(0, &mut game, &mut objects, &mut ui);
+    drop_item(0, &mut game, &mut objects);
+    assert!(game.inventory[0].equipment.unwrap().equipped);
+    assert_eq!(objects.len(), 1);
+
+    use_item(1, &mut game, &mut objects, &mut ui);
+    use_item(0, &mut game, &mut objects, &mut ui);
+    assert!(!game.inventory[0].equipment.unwrap().equipped);
+}
+
+#[test]
+fn identify_is_kept_with_nothing_to_identify() {
+    let mut game = test_game();
+    let mut objects = vec![test_player()];
+    game.inventory.push(new_item(Item::Identify, 0, 0));
+    let mut ui = headless_ui(&game.map, &[]);
+    use_item(0, &mut game, &mut objects, &mut ui);
+    assert_eq!(game.inventory.len(), 1);
+    assert!(game.log.iter().any(|message| message.0 == "You have nothing left to identify."));
+}
+
+#[test]
+fn magic_stays_hidden_until_identified() {
+    let mut game = test_game();
+    let mut objects = vec![test_player()];
+    let mut sword = enchanted(Item::Sword, magic(2, Some(Affix::Vampiric), None, false));
+    sword.enchantment.as_mut().unwrap().quality = Quality::Fine;
+    game.inventory.push(sword);
+    game.inventory.push(new_item(Item::Identify, 0, 0));
+    game.inventory.push(new_item(Item::Enchant, 0, 0));
+
+    assert_eq!(item_name(&game.inventory[0], &game), "fine sword");
+    assert_eq!(enchantment::known_equipment(&game.inventory[0]).unwrap().power_bonus, 4);
+    assert_eq!(game.inventory[0].equipment.unwrap().power_bonus, 6);
+
+    let mut ui = headless_ui(&game.map, &[key('a')]);
+    use_item(1, &mut game, &mut objects, &mut ui);
+    assert_eq!(item_name(&game.inventory[0], &game), "fine vampiric sword +2");
+
+    let mut ui = headless_ui(&game.map, &[key('a')]);
+    use_item(1, &mut game, &mut objects, &mut ui);
+    assert_eq!(item_name(&game.inventory[0], &game), "fine vampiric sword +3");
+    assert_eq!(game.inventory[0].equipment.unwrap().power_bonus, 7);
+}
+
+#[test]
+fn affixes_work_in_combat() {
+    let mut game = test_game();
+    let mut sword = enchanted(Item::Sword, magic(0, Some(Affix::Vampiric), Some(Affix::Fire), false));
+    sword.equipment.as_mut().unwrap().equipped = true;
+    let mut shield = enchanted(Item::Shield, magic(0, None, Some(Affix::Thorns), false));
+    shield.equipment.as_mut().unwrap().equipped = true;
+    game.inventory = vec![sword, shield];
+    let mut player = test_player();
+    player.fighter.as_mut().unwrap().hp = 20;
+    let mut orc = test_orc(11, 10);
+    orc.fighter = Some(fighter(20, 0, 10, 35, DeathCallback::Monster));
+
+    // 9 damage, a quarter of it drained, and 3 more from the fire.
+    player.attack(&mut game, &mut orc);
+    assert_eq!(hp(&orc), 8);
+    assert_eq!(hp(&player), 22);
+
+    orc.attack(&mut game, &mut player);
+    assert_eq!(hp(&player), 16);
+    assert_eq!(hp(&orc), 6);
+}
+
+#[test]
+fn a_monster_killed_by_thorns_stays_dead() {
+    let mut game = test_game();
+    let mut shield = enchanted(Item::Shield, magic(0, None, Some(Affix::Thorns), false));
+    shield.equipment.as_mut().unwrap().equipped = true;
+    game.inventory = vec![shield];
+    let mut objects = vec![test_player(), test_orc(11, 10)];
+    objects[1].fighter = Some(fighter(1, 0, 10, 35, DeathCallback::Monster));
+    let mut ui = headless_ui(&game.map, &[]);
+    update_fov(&mut ui, &mut objects, &mut game);
+
+    let noise = Noise::spread(&game.map, (10, 10), 0);
+    ai_take_turn(1, &mut game, &mut objects, &ui.fov, &noise);
+    assert!(!objects[1].alive);
+    assert!(objects[1].ai.is_none());
+    assert!(objects[1].fighter.is_none());
+}
+
+#[test]
+fn better_equipment_turns_up_deeper_down() {
+    dice::seed(5);
+    let qualities = |level| (0..200).map(|_| generate_item(Item::Sword, level, 0, 0).enchantment.unwrap().quality).collect::<Vec<_>>();
+    assert!(!qualities(1).contains(&Quality::Masterwork));
+    assert!(qualities(8).contains(&Quality::Masterwork));
+}
+
+fn worn_sword(current: i32) -> Object {
+    let mut sword = new_item(Item::Sword, 0, 0);
+    sword.equipment.as_mut().unwrap().equipped = true;
+    sword.durability.as_mut().unwrap().current = current;
+    sword
+}
+
+#[test]
+fn weapons_wear_with_every_hit_until_they_break() {
+    let mut game = test_game();
+    game.inventory.push(worn_sword(durability::WEAPON_DURABILITY / 2 + 1));
+    let mut player = test_player();
+    let mut orc = test_orc(11, 10);
+    orc.fighter = Some(fighter(100, 0, 3, 35, DeathCallback::Monster));
+    assert_eq!(player.power(&game), 9);
+
+    player.attack(&mut game, &mut orc);
+    assert!(game.inventory[0].durability.unwrap().is_damaged());
+    assert_eq!(player.power(&game), 7);
+
+    game.inventory[0].durability.as_mut().unwrap().current = 1;
+    player.attack(&mut game, &mut orc);
+    assert!(game.inventory.is_empty());
+    assert_eq!(game.log.last().unwrap().0, "Your sword breaks!");
+}
+
+#[test]
+fn digging_wears_only_the_pickaxe() {
+    let mut game = walled_in_game();
+    let mut pickaxe = new_item(Item::Pickaxe, 0, 0);
+    pickaxe.equipment.as_mut().unwrap().equipped = true;
+    let mut dagger = new_dagger();
+    dagger.equipment.as_mut().unwrap().equipped = true;
+    game.inventory = vec![pickaxe, dagger];
+    let mut objects = vec![test_player()];
+    objects[PLAYER].set_pos(11, 10);
+    let mut ui = headless_ui(&game.map, &[]);
+
+    player_move_or_attack(1, 0, &mut game, &mut objects, &mut ui);
+    assert_eq!(game.inventory[0].durability.unwrap().current, durability::PICKAXE_DURABILITY - 1);
+    assert_eq!(game.inventory[1].durability.unwrap().current, durability::WEAPON_DURABILITY);
+}
+
+#[test]
+fn armor_wears_only_when_a_blow_gets_through() {
+    let mut game = test_game();
+    let mut shield = new_item(Item::Shield, 0, 0);
+    shield.equipment.as_mut().unwrap().equipped = true;
+    game.inventory.push(shield);
+    let mut player = test_player();
+    let mut orc = test_orc(11, 10);
+
+    orc.attack(&mut game, &mut player);
+    assert_eq!(game.inventory[0].durability.unwrap().current, durability::ARMOR_DURABILITY);
+
+    orc.fighter.as_mut().unwrap().base_power = 10;
+    orc.attack(&mut game, &mut player);
+    assert_eq!(game.inventory[0].durability.unwrap().current, durability::ARMOR_DURABILITY - 1);
+}
+
+#[test]
+fn losing_a_sturdy_item_leaves_no_more_hp_than_the_new_maximum() {
+    let mut game = test_game();
+    let mut shield = enchanted(Item::Shield, magic(0, Some(Affix::Sturdy), None, false));
+    shield.equipment.as_mut().unwrap().equipped = true;
+    shield.durability.as_mut().unwrap().current = 1;
+    game.inventory.push(shield);
+    let mut player = test_player();
+    let max_hp = player.max_hp(&game);
+    player.fighter.as_mut().unwrap().hp = max_hp;
+    let mut orc = test_orc(11, 10);
+    orc.fighter.as_mut().unwrap().base_power = 5;
+
+    orc.attack(&mut game, &mut player);
+    assert!(game.inventory.is_empty());
+    assert_eq!(player.fighter.unwrap().hp, player.max_hp(&game));
+}
+
+#[test]
+fn repair_kits_and_anvils_mend_equipment() {
+    let mut game = test_game();
+    game.inventory = vec![worn_sword(10), new_item(Item::RepairKit, 0, 0)];
+    let mut objects = vec![test_player(), durability::new_anvil(11, 10)];
+    assert!(character_sheet(&objects[PLAYER], &game).contains("right hand: sword, nearly broken (10/80)"));
+
+    let mut ui = headless_ui(&game.map, &[key('a')]);
+    use_item(1, &mut game, &mut objects, &mut ui);
+    assert_eq!(game.inventory.len(), 1);
+    assert_eq!(game.inventory[0].durability.unwrap().current, durability::WEAPON_DURABILITY);
+
+    game.inventory[0].durability.as_mut().unwrap().current = 30;
+    player_move_or_attack(1, 0, &mut game, &mut objects, &mut ui);
+    assert_eq!(game.inventory[0].durability.unwrap().current, durability::WEAPON_DURABILITY);
+    assert_eq!(objects[1].anvil, Some(Anvil::Cracked));
+
+    game.inventory[0].durability.as_mut().unwrap().current = 30;
+    player_move_or_attack(1, 0, &mut game, &mut objects, &mut ui);
+    assert_eq!(game.inventory[0].durability.unwrap().current, 30);
+    assert_eq!(objects[PLAYER].pos(), (10, 10));
+}
@@ -0,0 +1,147 @@
+// Travelling: walking the player over several turns, one step a frame, without
+// a key press for each. It covers going to a chosen tile, exploring and running
+// in a straight line, and stops as soon as anything needs the player's
+// attention: a monster in view, a new item in sight, or lost HP.
+
+use pathfinding;
+
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Goal {
+    Tile(i32, i32),
+    // The nearest known floor next to somewhere unexplored, over and over.
+    Explore,
+    // One direction until something gets in the way or the surroundings
+    // change, like at a doorway or a corridor junction.
+    Run(i32, i32),
+}
+
+pub struct Travel {
+    goal: Goal,
+    hp: i32,
+    items_in_view: Vec<(i32, i32)>,
+    // How many open tiles were around the player after the last step, while
+    // running.
+    open_around: Option<usize>,
+}
+
+pub fn start(goal: Goal, ui: &mut Ui, objects: &[Object], game: &mut Game) {
+    if let Some(monster) = monster_in_view(objects, &ui.fov) {
+        game.log.add(format!("Not with the {} in view!", monster.name), colors::LIGHT_GREY);
+        return;
+    }
+    if let Goal::Tile(x, y) = goal {
+        let tile = &game.map[x as usize][y as usize];
+        if !tile.explored || tile.blocked {
+            game.log.add("You don't know a way there.", colors::LIGHT_GREY);
+            return;
+        }
+    }
+    ui.travel = Some(Travel {
+        goal,
+        hp: objects[PLAYER].fighter.map_or(0, |f| f.hp),
+        items_in_view: items_in_view(objects, &ui.fov),
+        open_around: None,
+    });
+}
+
+// The stairs, if the player has found them.
+pub fn known_stairs(objects: &[Object], game: &Game) -> Option<(i32, i32)> {
+    objects.iter()
+        .find(|object| object.name == "stairs" && game.map[object.x as usize][object.y as usize].explored)
+        .map(Object::pos)
+}
+
+// Takes the next step, or stops travelling if there is a reason to or nowhere
+// left to go.
+pub fn step(ui: &mut Ui, objects: &mut [Object], game: &mut Game) -> PlayerAction {
+    let next = match ui.travel.take() {
+        Some(mut travel) => {
+            let next = interruption(&travel, objects, game, &ui.fov).or_else(|| next_step(&mut travel, objects, game));
+            travel.hp = objects[PLAYER].fighter.map_or(0, |f| f.hp);
+            travel.items_in_view = items_in_view(objects, &ui.fov);
+            ui.travel = Some(travel);
+            next
+        },
+        None => return PlayerAction::DidntTakeTurn,
+    };
+
+    match next {
+        Some(Ok((x, y))) => {
+            let (player_x, player_y) = objects[PLAYER].pos();
+            player_move_or_attack(x - player_x, y - player_y, game, objects, ui);
+            PlayerAction::TookTurn
+        },
+        Some(Err(reason)) => {
+            game.log.add(reason, colors::LIGHT_GREY);
+            ui.travel = None;
+            PlayerAction::DidntTakeTurn
+        },
+        None => {
+            ui.travel = None;
+            PlayerAction::DidntTakeTurn
+        },
+    }
+}
+
+fn interruption(travel: &Travel, objects: &[Object], game: &Game, fov: &Vision) -> Option<Result<(i32, i32), String>> {
+    if let Some(monster) = monster_in_view(objects, fov) {
+        return Some(Err(format!("You stop at the sight of the {}.", monster.name)));
+    }
+    if objects[PLAYER].fighter.map_or(0, |f| f.hp) < travel.hp {
+        return Some(Err("You stop, hurt.".to_string()));
+    }
+    let spotted = objects.iter().find(|object| {
+        is_pickable(object) && fov.is_in_fov(object.x, object.y) && !travel.items_in_view.contains(&object.pos())
+    });
+    spotted.map(|item| Err(format!("You spot a {}.", item_name(item, game))))
+}
+
+// Where to step next: a tile, a reason there is no way on, or nothing if the
+// travel is simply over.
+fn next_step(travel: &mut Travel, objects: &[Object], game: &Game) -> Option<Result<(i32, i32), String>> {
+    let start = objects[PLAYER].pos();
+    let obstacle = |x: i32, y: i32| is_blocked(x, y, &game.map, objects) && !terrain::is_closed_door(&game.map[x as usize][y as usize]);
+    match travel.goal {
+        Goal::Tile(x, y) if start == (x, y) => None,
+        Goal::Tile(x, y) => {
+            let path = pathfinding::find_path(&game.map, start, obstacle, |px, py| (px, py) == (x, y));
+            Some(path.map(|path| path[0]).ok_or_else(|| "You can't find a way there.".to_string()))
+        },
+        Goal::Explore => {
+            let path = pathfinding::find_path(&game.map, start, obstacle, |x, y| pathfinding::borders_unexplored(x, y, &game.map));
+            Some(path.map(|path| path[0]).ok_or_else(|| "There is nowhere left to explore.".to_string()))
+        },
+        Goal::Run(dx, dy) => {
+            let open_around = pathfinding::neighbours(start.0, start.1, &game.map).iter()
+                .filter(|&&(x, y)| !game.map[x as usize][y as usize].blocked)
+                .count();
+            let changed = matches!(travel.open_around, Some(open) if open != open_around);
+            travel.open_around = Some(open_around);
+
+            let (x, y) = (start.0 + dx, start.1 + dy);
+            if changed || !in_map(x, y, &game.map) || obstacle(x, y) {
+                None
+            } else {
+                Some(Ok((x, y)))
+            }
+        },
+    }
+}
+
+fn monster_in_view<'a>(objects: &'a [Object], fov: &Vision) -> Option<&'a Object> {
+    objects.iter().find(|object| is_hostile(object) && fov.is_in_fov(object.x, object.y))
+}
+
+// Corpses don't count: there is always a reason to stop before one turns up.
+fn is_pickable(object: &Object) -> bool {
+    object.item.is_some() && object.item != Some(Item::Corpse)
+}
+
+fn items_in_view(objects: &[Object], fov: &Vision) -> Vec<(i32, i32)> {
+    objects.iter()
+        .filter(|object| is_pickable(object) && fov.is_in_fov(object.x, object.y))
+        .map(Object::pos)
+        .collect()
+}
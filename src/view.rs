@@ -0,0 +1,266 @@
+// The map part of the screen, kept between frames. Only tiles whose contents
+// can have changed since the last frame are worked out again: those that were
+// or are in the field of view, and those objects were or are drawn on.
+//
+// Maps can be bigger than the screen, so the view also holds a camera that
+// follows the player. Cells are kept in map coordinates and only translated to
+// the screen when drawn.
+
+use tcod::colors::lerp;
+
+use renderer::{ Cell, BLANK };
+use settings::Settings;
+
+use super::*;
+
+pub struct MapView {
+    width: i32,
+    height: i32,
+    cells: Vec<Cell>,
+    visible: Vec<(i32, i32)>,
+    drawn: Vec<(i32, i32)>,
+    // Tiles of the map itself that have changed, like a door opened out of
+    // sight.
+    changed: Vec<(i32, i32)>,
+    stale: bool,
+    camera: (i32, i32),
+    view_size: (i32, i32),
+    wall_glyph: char,
+    floor_glyph: char,
+}
+
+impl MapView {
+    pub fn new(settings: &Settings) -> MapView {
+        MapView {
+            width: 0,
+            height: 0,
+            cells: vec![],
+            visible: vec![],
+            drawn: vec![],
+            changed: vec![],
+            stale: true,
+            camera: (0, 0),
+            view_size: (0, 0),
+            wall_glyph: settings.wall_glyph,
+            floor_glyph: settings.floor_glyph,
+        }
+    }
+
+    // For changes the view can't see coming, like a new map or tiles explored
+    // out of sight.
+    pub fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    pub fn mark_changed(&mut self, x: i32, y: i32) {
+        self.changed.push((x, y));
+    }
+
+    pub fn update(&mut self, fov: &Vision, objects: &[Object], game: &Game, layout: &Layout) {
+        let mut dirty = if self.stale {
+            self.width = map_width(&game.map);
+            self.height = map_height(&game.map);
+            self.cells = vec![BLANK; (self.width * self.height) as usize];
+            let width = self.width;
+            (0..self.height).flat_map(|y| (0..width).map(move |x| (x, y))).collect()
+        } else {
+            let mut dirty = vec![];
+            dirty.append(&mut self.visible);
+            dirty.append(&mut self.drawn);
+            dirty.append(&mut self.changed);
+            dirty
+        };
+        self.changed.clear();
+        self.stale = false;
+
+        let player = objects[PLAYER].pos();
+        self.visible = fov.visible_tiles().to_vec();
+        dirty.extend_from_slice(&self.visible);
+
+        let mut to_draw: Vec<_> = objects.iter().filter(|o| {
+            fov.is_in_fov(o.x, o.y) ||
+            ((o.always_visible || o.seen) && game.map[o.x as usize][o.y as usize].explored)
+        }).collect();
+        to_draw.sort_by_key(|o| o.blocks);
+        self.drawn = to_draw.iter().map(|o| o.pos()).collect();
+
+        for &(x, y) in &dirty {
+            let tile = &game.map[x as usize][y as usize];
+            let visible = fov.is_in_fov(x, y);
+            let (dark, light) = if tile.block_sight {
+                (COLOR_DARK_WALL, COLOR_LIGHT_WALL)
+            } else {
+                (COLOR_DARK_GROUND, COLOR_LIGHT_GROUND)
+            };
+            // Tiles in view are brighter the more light falls on them.
+            let mut color = if visible { lerp(dark, light, 0.25 + 0.75 * fov.light(x, y)) } else { dark };
+            if tile.flammable {
+                color = lerp(color, colors::DARK_SEPIA, 0.5);
+            } else if tile.blocked && tile.cracked {
+                color = lerp(color, colors::DARK_GREY, 0.3);
+            } else if terrain::is_partly_dug(tile) {
+                color = lerp(color, colors::DARK_AMBER, 0.3);
+            }
+            // Effects change all the time, so they only show while in view.
+            match tile.effect {
+                Some(Effect::Fire { turns }) if visible => color = lerp(colors::ORANGE, colors::YELLOW, cmp::min(turns, 6) as f32 / 6.0),
+                Some(Effect::Gas { density }) if visible => color = lerp(color, colors::CHARTREUSE, cmp::min(density, 6) as f32 / 10.0 + 0.2),
+                Some(Effect::Water { depth }) if visible => color = lerp(color, colors::BLUE, cmp::min(depth, 3) as f32 / 6.0 + 0.2),
+                _ => {},
+            }
+            let glyph = match (tile.door, tile.blocked) {
+                (true, true) => '+',
+                (true, false) => '\'',
+                (false, true) => self.wall_glyph,
+                (false, false) => self.floor_glyph,
+            };
+            self.cells[(y * self.width + x) as usize] = if tile.explored {
+                Cell {
+                    c: glyph,
+                    fg: if !visible { colors::GREY } else if tile.door { colors::LIGHT_SEPIA } else { colors::WHITE },
+                    bg: color,
+                }
+            } else {
+                BLANK
+            };
+        }
+        // A pile of items shows how many there are, in the color of the top
+        // one, with anything blocking still drawn over it.
+        for object in &to_draw {
+            let pile = to_draw.iter().filter(|other| other.pos() == object.pos() && other.item.is_some()).count();
+            let cell = &mut self.cells[(object.y * self.width + object.x) as usize];
+            cell.c = if object.item.is_some() && pile > 1 { pile_glyph(pile) } else { object.char };
+            cell.fg = object.color;
+        }
+
+        self.view_size = (layout.view_width, layout.view_height);
+        self.camera = (camera_offset(player.0, layout.view_width, self.width),
+                       camera_offset(player.1, layout.view_height, self.height));
+    }
+
+    // Paints every cell of the view, over whatever a menu or the overview left
+    // there. Only the cells `update` marked dirty are worked out again, and
+    // both renderers only send the display cells that differ from the last
+    // frame, so this is a copy of the view's size whatever the map's.
+    pub fn draw(&self, renderer: &mut dyn Renderer) {
+        for screen_y in 0..self.view_size.1 {
+            for screen_x in 0..self.view_size.0 {
+                let (x, y) = (screen_x + self.camera.0, screen_y + self.camera.1);
+                let cell = if x >= 0 && y >= 0 && x < self.width && y < self.height {
+                    self.cells[(y * self.width + x) as usize]
+                } else {
+                    BLANK
+                };
+                renderer.set_background(screen_x, screen_y, cell.bg);
+                renderer.put_char(screen_x, screen_y, cell.c, cell.fg);
+            }
+        }
+    }
+
+    // What was last drawn for a map tile.
+    pub fn cell(&self, x: i32, y: i32) -> Cell {
+        self.cells[(y * self.width + x) as usize]
+    }
+
+    // Where a map tile is on screen, if it is within the view.
+    pub fn to_screen(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let (screen_x, screen_y) = (x - self.camera.0, y - self.camera.1);
+        if screen_x >= 0 && screen_y >= 0 && screen_x < self.view_size.0 && screen_y < self.view_size.1 {
+            Some((screen_x, screen_y))
+        } else {
+            None
+        }
+    }
+
+    // The map tile under a screen position, if the position is over the map.
+    pub fn to_world(&self, screen_x: i32, screen_y: i32) -> Option<(i32, i32)> {
+        if screen_x < 0 || screen_y < 0 || screen_x >= self.view_size.0 || screen_y >= self.view_size.1 {
+            return None;
+        }
+        let (x, y) = (screen_x + self.camera.0, screen_y + self.camera.1);
+        if x >= 0 && y >= 0 && x < self.width && y < self.height {
+            Some((x, y))
+        } else {
+            None
+        }
+    }
+
+    // The whole level shrunk to fit the view, each cell standing for a square
+    // block of tiles. A block shows the player, else the last object drawn in
+    // it, and is colored as floor if any of it is known floor.
+    pub fn draw_overview(&self, renderer: &mut dyn Renderer, game: &Game, player: (i32, i32)) {
+        let (scale, (left, top), (columns, rows)) = self.overview_geometry();
+        let block = |(x, y): (i32, i32)| ((y / scale) * columns + x / scale) as usize;
+
+        let mut blocks = vec![BLANK; (columns * rows) as usize];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let tile = &game.map[x as usize][y as usize];
+                let cell = &mut blocks[block((x, y))];
+                if tile.explored && (cell.bg == BLANK.bg || !tile.block_sight) {
+                    cell.bg = self.cells[(y * self.width + x) as usize].bg;
+                }
+            }
+        }
+        for &(x, y) in &self.drawn {
+            let drawn = self.cells[(y * self.width + x) as usize];
+            let cell = &mut blocks[block((x, y))];
+            cell.c = drawn.c;
+            cell.fg = drawn.fg;
+        }
+        let cell = &mut blocks[block(player)];
+        cell.c = '@';
+        cell.fg = colors::WHITE;
+
+        for row in 0..rows {
+            for column in 0..columns {
+                let cell = blocks[(row * columns + column) as usize];
+                renderer.set_background(left + column, top + row, cell.bg);
+                renderer.put_char(left + column, top + row, cell.c, cell.fg);
+            }
+        }
+    }
+
+    // The tiles an overview cell stands for, nearest its middle first.
+    pub fn overview_tiles(&self, screen_x: i32, screen_y: i32) -> Vec<(i32, i32)> {
+        let (scale, (left, top), (columns, rows)) = self.overview_geometry();
+        let (column, row) = (screen_x - left, screen_y - top);
+        if column < 0 || row < 0 || column >= columns || row >= rows {
+            return vec![];
+        }
+        let middle = (column * scale + scale / 2, row * scale + scale / 2);
+        let mut tiles: Vec<_> = (row * scale..cmp::min((row + 1) * scale, self.height))
+            .flat_map(|y| (column * scale..cmp::min((column + 1) * scale, self.width)).map(move |x| (x, y)))
+            .collect();
+        tiles.sort_by_key(|&(x, y)| (x - middle.0).pow(2) + (y - middle.1).pow(2));
+        tiles
+    }
+
+    // Tiles per cell, where the overview starts on screen, and its size in
+    // cells.
+    fn overview_geometry(&self) -> (i32, (i32, i32), (i32, i32)) {
+        let (view_width, view_height) = (cmp::max(1, self.view_size.0), cmp::max(1, self.view_size.1));
+        let scale = cmp::max(1, cmp::max(ceil_div(self.width, view_width), ceil_div(self.height, view_height)));
+        let size = (ceil_div(self.width, scale), ceil_div(self.height, scale));
+        (scale, ((view_width - size.0) / 2, (view_height - size.1) / 2), size)
+    }
+}
+
+fn ceil_div(a: i32, b: i32) -> i32 {
+    (a + b - 1) / b
+}
+
+// Keeps the player in the middle of the view until the edge of the map comes
+// into sight. Maps smaller than the view are centred instead.
+fn camera_offset(player: i32, view_size: i32, map_size: i32) -> i32 {
+    if map_size <= view_size {
+        (map_size - view_size) / 2
+    } else {
+        cmp::max(0, cmp::min(player - view_size / 2, map_size - view_size))
+    }
+}
+
+// The count of a pile of items, up to 9 for nine or more.
+fn pile_glyph(count: usize) -> char {
+    std::char::from_digit(cmp::min(count, 9) as u32, 10).unwrap()
+}